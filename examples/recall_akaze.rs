@@ -1,10 +1,10 @@
 extern crate std;
 
 use bitarray::{BitArray, Hamming};
-use hgg::Hgg;
+use hgg::{Hgg, SearchParams};
 use rand::{seq::SliceRandom, Rng, SeedableRng};
 use serde::Serialize;
-use space::{Knn, KnnInsert};
+use space::KnnInsert;
 use std::{io::Read, time::Instant};
 
 // Dataset sizes.
@@ -131,11 +131,14 @@ fn main() {
 
         for knn in 1..=HIGHEST_KNN {
             eprintln!("doing size {} with knn {}", 1 << pow, knn);
-            hgg = hgg.search_extra_knn(knn - 1);
+            let params = SearchParams {
+                search_extra_knn: knn - 1,
+                ..hgg.search_params()
+            };
             let start_time = Instant::now();
             let hgg_nn_distances: Vec<_> = test
                 .iter()
-                .map(|query| hgg.nn(query).unwrap().distance)
+                .map(|query| hgg.knn_with(query, 1, &params)[0].distance)
                 .collect();
             let end_time = Instant::now();
             let num_correct = correct_nn_distances
@@ -0,0 +1,63 @@
+extern crate std;
+
+use bitarray::{BitArray, Hamming};
+use hgg::{Hgg, HggLite, SearchContext, VisitedSet};
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+// The number of keys searched over can be passed as the first argument.
+const DEFAULT_SEARCH_SPACE: usize = 1 << 16;
+const TEST_QUERRIES: usize = 1 << 14;
+const KNN: usize = 16;
+
+fn main() {
+    let search_space = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("expected the number of keys"))
+        .unwrap_or(DEFAULT_SEARCH_SPACE);
+
+    // Use a PRNG with good statistical properties for generating 64-bit numbers.
+    let mut rng = rand_xoshiro::Xoshiro256PlusPlus::seed_from_u64(0);
+    let mut keys = (&mut rng)
+        .sample_iter::<[u8; 32], _>(rand::distributions::Standard)
+        .map(BitArray::new);
+    let search: Vec<BitArray<32>> = (&mut keys).take(search_space).collect();
+    let queries: Vec<BitArray<32>> = keys.take(TEST_QUERRIES).collect();
+
+    eprintln!("Inserting {} keys...", search_space);
+    let mut hgg: Hgg<Hamming, BitArray<32>, ()> = Hgg::new(Hamming).insert_knn(64);
+    hgg.insert_batch(search.iter().map(|&key| (key, ())));
+    let mut lite: HggLite<Hamming, BitArray<32>, ()> = HggLite::new(Hamming).insert_knn(64);
+    lite.insert_batch(search.iter().map(|&key| (key, ())));
+
+    for visited in [VisitedSet::Hash, VisitedSet::Epoch] {
+        hgg.set_visited(visited);
+        lite.set_visited(visited);
+        // Search once beforehand so that each context has grown to fit its graph.
+        let mut ctx = SearchContext::new();
+        let mut lite_ctx = SearchContext::new();
+        hgg.knn_in(&mut ctx, &queries[0], KNN);
+        lite.knn_in(&mut lite_ctx, &queries[0], KNN);
+
+        let start = Instant::now();
+        for query in &queries {
+            hgg.knn_in(&mut ctx, query, KNN);
+        }
+        report("Hgg", visited, start.elapsed().as_secs_f64());
+
+        let start = Instant::now();
+        for query in &queries {
+            lite.knn_in(&mut lite_ctx, query, KNN);
+        }
+        report("HggLite", visited, start.elapsed().as_secs_f64());
+    }
+}
+
+fn report(graph: &str, visited: VisitedSet, seconds: f64) {
+    println!(
+        "{} with {:?}: {:.0} queries per second",
+        graph,
+        visited,
+        TEST_QUERRIES as f64 / seconds
+    );
+}
@@ -0,0 +1,204 @@
+use crate::{Hgg, HggCore, HggLite, NodeIndex};
+use core::fmt::{self, Display, Write};
+use space::Metric;
+
+impl<M, K, V, HK, Strategy, Ix> HggCore<M, K, V, HK, Strategy, Ix>
+where
+    M: Metric<K>,
+    Ix: NodeIndex,
+{
+    /// Iterates over every edge of every layer once as `(layer, node, neighbor, distance)`, where `node < neighbor`.
+    fn undirected_edges(&self) -> impl Iterator<Item = (usize, usize, usize, M::Unit)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .flat_map(move |(node, hnode)| {
+                hnode
+                    .layers
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(layer, edges)| {
+                        edges
+                            .as_slice()
+                            .iter()
+                            .map(|edge| edge.neighbor.node.index())
+                            .filter(move |&neighbor| node < neighbor)
+                            .map(move |neighbor| {
+                                let distance = self
+                                    .metric
+                                    .distance(&self.nodes[node].key, &self.nodes[neighbor].key);
+                                (layer, node, neighbor, distance)
+                            })
+                    })
+            })
+    }
+
+    /// Writes the graph in the GraphViz DOT format.
+    fn write_dot(&self, out: &mut impl Write) -> fmt::Result
+    where
+        M::Unit: Display,
+    {
+        writeln!(out, "graph hgg {{")?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                out,
+                "    {} [height={}, deleted={}];",
+                index,
+                node.layers(),
+                node.deleted
+            )?;
+        }
+        for (layer, node, neighbor, distance) in self.undirected_edges() {
+            writeln!(
+                out,
+                "    {} -- {} [layer={}, distance={}];",
+                node, neighbor, layer, distance
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Writes the graph in the GraphML format.
+    fn write_graphml(&self, out: &mut impl Write) -> fmt::Result
+    where
+        M::Unit: Display,
+    {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="height" for="node" attr.name="height" attr.type="long"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="deleted" for="node" attr.name="deleted" attr.type="boolean"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="layer" for="edge" attr.name="layer" attr.type="long"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="distance" for="edge" attr.name="distance" attr.type="double"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="hgg" edgedefault="undirected">"#)?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                out,
+                r#"    <node id="n{}"><data key="height">{}</data><data key="deleted">{}</data></node>"#,
+                index,
+                node.layers(),
+                node.deleted
+            )?;
+        }
+        for (layer, node, neighbor, distance) in self.undirected_edges() {
+            writeln!(
+                out,
+                r#"    <edge source="n{}" target="n{}"><data key="layer">{}</data><data key="distance">{}</data></edge>"#,
+                node, neighbor, layer, distance
+            )?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+
+    /// Writes the graph as a JSON object containing a list of nodes and a list of edges.
+    fn write_json(&self, out: &mut impl Write) -> fmt::Result
+    where
+        M::Unit: Display,
+    {
+        write!(out, r#"{{"nodes":["#)?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            if index != 0 {
+                write!(out, ",")?;
+            }
+            write!(
+                out,
+                r#"{{"id":{},"height":{},"deleted":{}}}"#,
+                index,
+                node.layers(),
+                node.deleted
+            )?;
+        }
+        write!(out, r#"],"edges":["#)?;
+        for (index, (layer, node, neighbor, distance)) in self.undirected_edges().enumerate() {
+            if index != 0 {
+                write!(out, ",")?;
+            }
+            write!(
+                out,
+                r#"{{"source":{},"target":{},"layer":{},"distance":{}}}"#,
+                node, neighbor, layer, distance
+            )?;
+        }
+        write!(out, "]}}")
+    }
+}
+
+impl<M, K, V, Ix> Hgg<M, K, V, Ix>
+where
+    M: Metric<K>,
+    M::Unit: Display,
+    K: Clone,
+    Ix: NodeIndex,
+{
+    /// Writes the graph in the GraphViz DOT format.
+    ///
+    /// Every node has a `height` attribute, which is the number of layers it exists on, and a `deleted` attribute.
+    /// Every edge of every layer is written once with a `layer` attribute and the `distance` between its nodes.
+    pub fn write_dot(&self, out: &mut impl Write) -> fmt::Result {
+        self.hgg.write_dot(out)
+    }
+
+    /// Writes the graph in the GraphML format, which can be opened with tools such as Gephi.
+    ///
+    /// Every node has a `height` attribute, which is the number of layers it exists on, and a `deleted` attribute.
+    /// Every edge of every layer is written once with a `layer` attribute and the `distance` between its nodes.
+    pub fn write_graphml(&self, out: &mut impl Write) -> fmt::Result {
+        self.hgg.write_graphml(out)
+    }
+
+    /// Writes the graph as a JSON edge list.
+    ///
+    /// The object has a `nodes` list of `{"id", "height", "deleted"}` objects, where `height` is the number of layers
+    /// the node exists on, and an `edges` list of `{"source", "target", "layer", "distance"}` objects containing every
+    /// edge of every layer once.
+    pub fn write_json(&self, out: &mut impl Write) -> fmt::Result {
+        self.hgg.write_json(out)
+    }
+}
+
+impl<M, K, V, Ix> HggLite<M, K, V, Ix>
+where
+    M: Metric<K>,
+    M::Unit: Display,
+    Ix: NodeIndex,
+{
+    /// Writes the graph in the GraphViz DOT format.
+    ///
+    /// Every node has a `height` attribute, which is the number of layers it exists on, and a `deleted` attribute.
+    /// Every edge of every layer is written once with a `layer` attribute and the `distance` between its nodes.
+    pub fn write_dot(&self, out: &mut impl Write) -> fmt::Result {
+        self.hgg.write_dot(out)
+    }
+
+    /// Writes the graph in the GraphML format, which can be opened with tools such as Gephi.
+    ///
+    /// Every node has a `height` attribute, which is the number of layers it exists on, and a `deleted` attribute.
+    /// Every edge of every layer is written once with a `layer` attribute and the `distance` between its nodes.
+    pub fn write_graphml(&self, out: &mut impl Write) -> fmt::Result {
+        self.hgg.write_graphml(out)
+    }
+
+    /// Writes the graph as a JSON edge list.
+    ///
+    /// The object has a `nodes` list of `{"id", "height", "deleted"}` objects, where `height` is the number of layers
+    /// the node exists on, and an `edges` list of `{"source", "target", "layer", "distance"}` objects containing every
+    /// edge of every layer once.
+    pub fn write_json(&self, out: &mut impl Write) -> fmt::Result {
+        self.hgg.write_json(out)
+    }
+}
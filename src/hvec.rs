@@ -7,21 +7,21 @@ use core::{
 use header_vec::HeaderVecWeak;
 
 #[derive(Debug)]
-pub(crate) struct HggEdge<K> {
+pub(crate) struct HggEdge<K, Ix> {
     pub(crate) key: K,
-    pub(crate) neighbor: HVec<K>,
+    pub(crate) neighbor: HVec<K, Ix>,
 }
 
 #[derive(Debug)]
-pub(crate) struct HggHeader<K> {
+pub(crate) struct HggHeader<K, Ix> {
     pub(crate) key: K,
-    pub(crate) node: usize,
+    pub(crate) node: Ix,
 }
 
 #[derive(Debug)]
-pub(crate) struct HVec<K>(pub(crate) HeaderVecWeak<HggHeader<K>, HggEdge<K>>);
+pub(crate) struct HVec<K, Ix>(pub(crate) HeaderVecWeak<HggHeader<K, Ix>, HggEdge<K, Ix>>);
 
-impl<K> HVec<K> {
+impl<K, Ix> HVec<K, Ix> {
     pub fn weak(&self) -> Self {
         unsafe { Self(self.0.weak()) }
     }
@@ -33,31 +33,37 @@ impl<K> HVec<K> {
     }
 }
 
-impl<K> Deref for HVec<K> {
-    type Target = HeaderVecWeak<HggHeader<K>, HggEdge<K>>;
+impl<K, Ix> Clone for HVec<K, Ix> {
+    fn clone(&self) -> Self {
+        self.weak()
+    }
+}
+
+impl<K, Ix> Deref for HVec<K, Ix> {
+    type Target = HeaderVecWeak<HggHeader<K, Ix>, HggEdge<K, Ix>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl<K> DerefMut for HVec<K> {
+impl<K, Ix> DerefMut for HVec<K, Ix> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
-impl<K> PartialEq for HVec<K> {
+impl<K, Ix> PartialEq for HVec<K, Ix> {
     fn eq(&self, other: &Self) -> bool {
         self.ptr().eq(&other.ptr())
     }
 }
 
-impl<K> Eq for HVec<K> {}
+impl<K, Ix> Eq for HVec<K, Ix> {}
 
-impl<K> PartialOrd for HVec<K> {
+impl<K, Ix> PartialOrd for HVec<K, Ix> {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        self.ptr().partial_cmp(&other.ptr())
+        Some(self.cmp(other))
     }
 
     fn lt(&self, other: &Self) -> bool {
@@ -74,13 +80,13 @@ impl<K> PartialOrd for HVec<K> {
     }
 }
 
-impl<K> Ord for HVec<K> {
+impl<K, Ix> Ord for HVec<K, Ix> {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
         self.ptr().cmp(&other.ptr())
     }
 }
 
-impl<K> Hash for HVec<K> {
+impl<K, Ix> Hash for HVec<K, Ix> {
     fn hash<H>(&self, hasher: &mut H)
     where
         H: Hasher,
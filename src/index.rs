@@ -0,0 +1,46 @@
+use core::{convert::TryFrom, fmt::Debug};
+
+/// The integer type used to store the indices of nodes inside of the graph.
+///
+/// Every layer of every node stores the index of its node, and every node stores the index of the next node
+/// in the freshening order, so a smaller index type saves memory in the graph and in its serialized form.
+/// The default of `usize` supports any number of nodes, while `u32` supports up to `u32::MAX` nodes on
+/// 64-bit targets at half the size. The methods of the graph always take and return `usize` indices regardless.
+///
+/// Inserting a node whose index is larger than [`NodeIndex::max`] panics before the graph is changed.
+pub trait NodeIndex: Copy + Debug + Eq + Ord + Send + Sync + 'static {
+    /// Converts a node index into this type.
+    ///
+    /// Panics if the index is too large to be represented.
+    fn new(index: usize) -> Self;
+
+    /// Converts this back into a node index.
+    fn index(self) -> usize;
+
+    /// The largest node index that can be represented.
+    fn max() -> usize;
+}
+
+macro_rules! impl_node_index {
+    ($($ty:ty),*) => {$(
+        impl NodeIndex for $ty {
+            #[inline(always)]
+            fn new(index: usize) -> Self {
+                <$ty>::try_from(index).expect("node index exceeds the capacity of the node index type")
+            }
+
+            #[inline(always)]
+            fn index(self) -> usize {
+                self as usize
+            }
+
+            #[inline(always)]
+            fn max() -> usize {
+                // A `usize` could be smaller than the index type, in which case `usize::MAX` is the limit.
+                usize::try_from(<$ty>::MAX).unwrap_or(usize::MAX)
+            }
+        }
+    )*};
+}
+
+impl_node_index!(u16, u32, u64, usize);
@@ -1,28 +1,54 @@
 #![no_std]
 extern crate alloc;
 
+mod export;
 mod hvec;
+mod index;
+mod linear;
+#[cfg(feature = "rayon")]
+mod rayon_impl;
+#[cfg(feature = "std")]
+mod recall;
+mod search;
 #[cfg(feature = "serde")]
 mod serde_impl;
 #[cfg(test)]
 mod unit_tests;
+#[cfg(feature = "zerocopy")]
+mod view;
+
+pub use index::NodeIndex;
+pub use linear::{ExhaustiveKnn, LinearKnn};
+#[cfg(feature = "std")]
+pub use recall::{evaluate_recall, RecallReport};
+pub use search::{SearchContext, SearchVisitor};
+#[cfg(feature = "serde")]
+pub use serde_impl::{LegacyHgg, LegacyHggLite};
+#[cfg(feature = "zerocopy")]
+pub use view::{HggView, HggViewError};
 
 use ahash::RandomState;
 use alloc::{vec, vec::Vec};
 use core::{
-    fmt::Debug,
+    cmp,
+    fmt::{self, Debug, Display},
     iter,
     marker::PhantomData,
-    ops::{Deref, DerefMut},
+    mem,
+    ops::{Deref, DerefMut, Range},
 };
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use header_vec::HeaderVec;
 use hvec::{HVec, HggEdge, HggHeader};
 use num_traits::Zero;
+use search::{KnnIter, Scratch, SearchGraph};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use space::{Knn, KnnInsert, KnnMap, KnnPoints, Metric, Neighbor};
 
+/// The number of entries inserted together by [`Hgg::insert_batch`] and [`HggLite::insert_batch`].
+const BATCH_CHUNK_LEN: usize = 256;
+
 #[derive(Debug)]
 struct StrategyRegular;
 #[derive(Debug)]
@@ -40,24 +66,33 @@ struct StrategyLite;
 ///
 /// Always remember to benchmark rather than guess when it comes to the above choices.
 ///
+/// [`Hgg`] is [`Send`] and [`Sync`] when its metric, keys, and values are, so it can be shared between threads
+/// in an `Arc` and searched concurrently. With the `rayon` feature enabled, `knn_batch` and `nn_batch`
+/// search many queries in parallel.
+///
+/// With the `serde` feature enabled, it is serialized with a header containing a format version and checksum,
+/// and it is checked for corruption when it is deserialized. The checksum only covers the structure of the graph
+/// and its counts, not the keys, the values, or the `visited` and `repair_unreachable` settings, so corruption
+/// of those is only caught if they can't be deserialized. The indices are written as `Ix`, so a graph must be
+/// deserialized with the same [`NodeIndex`] type that it was serialized with.
+///
+/// With the `zerocopy` feature enabled, `to_view_bytes` writes it in a flat layout that can be memory-mapped
+/// and searched with `HggView` without deserializing it.
+///
+/// The indices of nodes are stored as `Ix`, which is `usize` by default. Use [`Hgg::with_node_index`] to store
+/// them as a smaller [`NodeIndex`] type, such as `u32`, when the graph will never hold that many nodes.
+///
 /// If you are looking for how to perform kNN searches, see `impl<K, V> Knn<K> for Hgg<K, V>` below.
-#[derive(Debug)]
-#[cfg_attr(
-    feature = "serde",
-    derive(Serialize, Deserialize),
-    serde(bound(
-        serialize = "M: Serialize, K: Serialize, V: Serialize",
-        deserialize = "M: Deserialize<'de>, K: Deserialize<'de> + Clone, V: Deserialize<'de>"
-    ))
-)]
-pub struct Hgg<M, K, V> {
-    hgg: HggCore<M, K, V, K, StrategyRegular>,
+#[derive(Debug, Clone)]
+pub struct Hgg<M, K, V, Ix: NodeIndex = usize> {
+    hgg: HggCore<M, K, V, K, StrategyRegular, Ix>,
 }
 
-impl<M, K, V> Knn for Hgg<M, K, V>
+impl<M, K, V, Ix> Knn for Hgg<M, K, V, Ix>
 where
     M: Metric<K>,
     K: Clone,
+    Ix: NodeIndex,
 {
     type Ix = usize;
     type Metric = M;
@@ -66,26 +101,35 @@ where
 
     fn knn(&self, query: &K, num: usize) -> Self::KnnIter {
         self.hgg
-            .search_knn(query, num)
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                &mut (),
+            )
+            .into_iter()
             .map(|(index, distance)| Neighbor { index, distance })
             .collect()
     }
 }
 
-impl<M, K, V> KnnPoints for Hgg<M, K, V>
+impl<M, K, V, Ix> KnnPoints for Hgg<M, K, V, Ix>
 where
     M: Metric<K>,
     K: Clone,
+    Ix: NodeIndex,
 {
     fn get_point(&self, index: usize) -> &'_ K {
         self.get_key(index).unwrap()
     }
 }
 
-impl<M, K, V> KnnMap for Hgg<M, K, V>
+impl<M, K, V, Ix> KnnMap for Hgg<M, K, V, Ix>
 where
     M: Metric<K>,
     K: Clone,
+    Ix: NodeIndex,
 {
     type Value = V;
     fn get_value(&self, index: usize) -> &'_ V {
@@ -93,10 +137,11 @@ where
     }
 }
 
-impl<M, K, V> KnnInsert for Hgg<M, K, V>
+impl<M, K, V, Ix> KnnInsert for Hgg<M, K, V, Ix>
 where
     M: Metric<K>,
     K: Clone,
+    Ix: NodeIndex,
 {
     fn insert(&mut self, key: Self::Point, value: Self::Value) -> usize {
         self.hgg.insert(key, value)
@@ -115,11 +160,38 @@ where
         }
     }
 
+    /// Creates a new [`Hgg`] with default settings from a batch of (key, value) pairs.
+    ///
+    /// See [`Hgg::insert_batch`] for details. The nodes are numbered in the order of `entries`.
+    pub fn from_batch(metric: M, entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut hgg = Self::new(metric);
+        hgg.insert_batch(entries);
+        hgg
+    }
+}
+
+impl<M, K, V, Ix> Hgg<M, K, V, Ix>
+where
+    M: Metric<K>,
+    K: Clone,
+    Ix: NodeIndex,
+{
+    /// Creates a new [`Hgg`] which stores the indices of its nodes as `Ix` instead of `usize`.
+    /// It will be empty and begin with default settings.
+    ///
+    /// See [`NodeIndex`] for details.
+    pub fn with_node_index(metric: M) -> Self {
+        Self {
+            hgg: HggCore::new(metric),
+        }
+    }
+
     /// Default value: `16`
     ///
     /// Decrease this to speed up at the expense of recall, and vice versa.
     ///
     /// The number of nearest neighbors to add on top of the number requested to increase recall.
+    /// This is also the number of nodes outside of the radius that are searched through in a radius search.
     pub fn search_extra_knn(self, search_extra_knn: usize) -> Self {
         Self {
             hgg: self.hgg.search_extra_knn(search_extra_knn),
@@ -151,6 +223,21 @@ where
         }
     }
 
+    /// Default value: [`VisitedSet::Hash`]
+    ///
+    /// Chooses how searches keep track of the nodes they have already added to their pool of candidates.
+    /// [`VisitedSet::Epoch`] needs a slot for every node, which searches that don't reuse their scratch space
+    /// have to allocate every time, so it works best with [`Self::knn_in`], [`Self::insert_in`] and `knn_batch`.
+    /// It also needs the index of every neighbor, which [`Hgg`] has to read from the neighbor's node
+    /// since its edges only hold the neighbor's key. This usually makes it slower than [`VisitedSet::Hash`] here,
+    /// so prefer [`HggLite`], which reads the neighbor's node anyway, if you want to use it.
+    /// The `visited` example compares the two.
+    pub fn visited(self, visited: VisitedSet) -> Self {
+        Self {
+            hgg: self.hgg.visited(visited),
+        }
+    }
+
     /// Default value: `64`
     ///
     /// This controls the number of nearest neighbors used during insertion. Setting this higher will cause the graph
@@ -167,16 +254,259 @@ where
         }
     }
 
+    /// Default value: `false`
+    ///
+    /// If this is true, every node that is freshened is also checked to see if greedy search from the root
+    /// finds it when searching for its own key (see [`Self::unreachable`]). If it doesn't, the node is disconnected
+    /// and inserted again. This makes freshening slower, so only enable it if greedy self-recall matters to you.
+    pub fn repair_unreachable(self, repair_unreachable: bool) -> Self {
+        Self {
+            hgg: self.hgg.repair_unreachable(repair_unreachable),
+        }
+    }
+
+    /// Sets [`Hgg::search_extra_knn`] without consuming the index.
+    pub fn set_search_extra_knn(&mut self, search_extra_knn: usize) {
+        self.hgg.set_search_extra_knn(search_extra_knn);
+    }
+
+    /// Sets [`Hgg::freshens`] without consuming the index.
+    pub fn set_freshens(&mut self, freshens: usize) {
+        self.hgg.set_freshens(freshens);
+    }
+
+    /// Sets [`Hgg::exclude_all_searched`] without consuming the index.
+    pub fn set_exclude_all_searched(&mut self, exclude_all_searched: bool) {
+        self.hgg.set_exclude_all_searched(exclude_all_searched);
+    }
+
+    /// Sets [`Hgg::visited`] without consuming the index.
+    pub fn set_visited(&mut self, visited: VisitedSet) {
+        self.hgg.set_visited(visited);
+    }
+
+    /// Sets [`Hgg::insert_knn`] without consuming the index.
+    pub fn set_insert_knn(&mut self, insert_knn: usize) {
+        self.hgg.set_insert_knn(insert_knn);
+    }
+
+    /// Sets [`Hgg::repair_unreachable`] without consuming the index.
+    pub fn set_repair_unreachable(&mut self, repair_unreachable: bool) {
+        self.hgg.set_repair_unreachable(repair_unreachable);
+    }
+
+    /// Returns the parameters used by [`Knn::knn`].
+    pub fn search_params(&self) -> SearchParams {
+        self.hgg.search_params()
+    }
+
+    /// Sets the parameters used by [`Knn::knn`].
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.hgg.set_search_params(params);
+    }
+
+    /// Finds the `num` nearest neighbors of the query using the given search parameters
+    /// instead of the ones stored in the index.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_with(&self, query: &K, num: usize, params: &SearchParams) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                params,
+                &mut (),
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds the `num` nearest neighbors of the query like [`Knn::knn`], using the scratch space of `ctx`.
+    ///
+    /// Reusing one [`SearchContext`] for every search on a thread avoids allocating scratch space for each search.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_in(
+        &self,
+        ctx: &mut SearchContext<K, M::Unit, Ix>,
+        query: &K,
+        num: usize,
+    ) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn(
+                ctx.hgg_scratch(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                &mut (),
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds the `num` nearest neighbors of the query, calling the `visitor` on every step of the search.
+    ///
+    /// The search is exactly the same as [`Knn::knn`], so this can be used to trace the path taken by a query
+    /// that returns bad results.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_visit(
+        &self,
+        query: &K,
+        num: usize,
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                visitor,
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds the `num` nearest neighbors of the query like [`Self::knn_with`], and counts the work done by the search.
+    ///
+    /// This is useful for tuning the search parameters and [`Self::insert_knn`], especially with expensive metrics.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest along with the counters.
+    pub fn knn_with_stats(
+        &self,
+        query: &K,
+        num: usize,
+        params: &SearchParams,
+    ) -> (Vec<Neighbor<M::Unit>>, SearchStats) {
+        let mut stats = SearchStats::default();
+        let neighbors = self
+            .hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                params,
+                &mut stats,
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect();
+        (neighbors, stats)
+    }
+
+    /// Finds the `num` nearest neighbors of the query like [`Knn::knn`], but stops early once the `budget` runs out.
+    ///
+    /// This bounds the work done by a search, which is useful when there is a hard limit on latency.
+    ///
+    /// The limits also apply to the greedy search through the upper layers, so a search which stops there only
+    /// returns the node it reached, or nothing if that node is marked as deleted. Only nodes which aren't marked
+    /// as deleted count towards [`SearchBudget::good_enough`].
+    ///
+    /// Returns the best neighbors found so far sorted from nearest to furthest, along with how the search ended.
+    pub fn knn_with_budget(
+        &self,
+        query: &K,
+        num: usize,
+        budget: &SearchBudget<M::Unit>,
+    ) -> (Vec<Neighbor<M::Unit>>, BudgetOutcome) {
+        let mut visitor = BudgetVisitor::new(*budget, |node: usize| !self.hgg.nodes[node].deleted);
+        let neighbors = self
+            .hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                &mut visitor,
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect();
+        (neighbors, visitor.outcome)
+    }
+
+    /// Finds the nearest neighbors of the query lazily, searching further as more neighbors are taken.
+    ///
+    /// This is useful when the number of neighbors needed isn't known beforehand, such as when looking for the
+    /// nearest neighbor which matches some condition. The search keeps [`Self::search_extra_knn`] extra nodes
+    /// ahead of the neighbors it returns, like [`Knn::knn`]. Nodes marked as deleted are not returned.
+    ///
+    /// Returns the neighbors from nearest to furthest, except that a node found only after a further neighbor
+    /// was returned is still returned, just out of order. Each neighbor returned is the nearest of the nodes found
+    /// so far, so once [`Self::search_extra_knn`] is at least the number of nodes, the whole graph is found before
+    /// the first neighbor is returned and the order is exact. Iterating to the end returns every node which is not
+    /// marked as deleted.
+    pub fn knn_iter<'a>(&'a self, query: &'a K) -> impl Iterator<Item = Neighbor<M::Unit>> + 'a {
+        KnnIter::new(&self.hgg, query, &self.hgg.search_params(), move |node| {
+            !self.hgg.nodes[node].deleted
+        })
+        .map(|(index, distance)| Neighbor { index, distance })
+    }
+
     /// Searches for the nearest neighbor greedily from the top layer to the bottom.
     ///
     /// This does not utilize [`Self::search_extra_knn`]. It is specialized to search greedily
-    /// keeping track of only one best node at a time.
+    /// keeping track of only one best node at a time. It may return a node marked as deleted.
     ///
     /// Returns `(node, distance)`.
     pub fn greedy(&self, query: &K) -> Option<(usize, M::Unit)> {
         self.hgg.search(query)
     }
 
+    /// Finds the `num` nearest neighbors of the query which pass the `filter`.
+    ///
+    /// The filter is given the index, key, and value of each node. Nodes that don't pass the filter are still
+    /// searched through so that the graph remains navigable, but they never appear in the results. This has much
+    /// better recall than filtering the results of [`Knn::knn`]. Nodes marked as deleted are not returned.
+    ///
+    /// Every node that is searched through is kept until `num` nodes have passed the filter, and there is no limit
+    /// on how many that can be. If few nodes near the query pass the filter, the search grows until it finds them,
+    /// and if fewer than `num` nodes pass at all, it searches the whole graph. When a filter rejects most of the
+    /// graph, it can be faster to search the accepted nodes directly.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_filtered(
+        &self,
+        query: &K,
+        num: usize,
+        filter: impl Fn(usize, &K, &V) -> bool,
+    ) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn_filtered_entries(query, num, filter)
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds every node within `radius` of the query.
+    ///
+    /// This is [`Self::within_with`] with a `slack` of [`Self::search_extra_knn`].
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn within(&self, query: &K, radius: M::Unit) -> Vec<Neighbor<M::Unit>> {
+        self.within_with(query, radius, self.hgg.search_extra_knn)
+    }
+
+    /// Finds every node within `radius` of the query, searching through up to `slack` nodes outside of it.
+    ///
+    /// Every node found within the radius is searched through, along with up to `slack` of the nearest nodes
+    /// found outside of it, since some nodes within the radius may only be connected through nodes outside of it.
+    /// Increase `slack` to improve recall at the expense of speed. Nodes marked as deleted are not returned.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn within_with(&self, query: &K, radius: M::Unit, slack: usize) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_within(query, radius, slack)
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
     /// Get the (key, value) pair of a node.
     pub fn get(&self, node: usize) -> Option<(&K, &V)> {
         self.hgg.get(node)
@@ -192,12 +522,160 @@ where
         self.hgg.get_value(node)
     }
 
+    /// Get a mutable reference to the value of a node.
+    ///
+    /// The key can't be mutated this way, as the graph depends on it. Use [`Self::update_key`] instead.
+    pub fn get_value_mut(&mut self, node: usize) -> Option<&mut V> {
+        self.hgg.get_value_mut(node)
+    }
+
+    /// Iterates over the `(node, key, value)` entries in order of their node index.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &K, &V)> + '_ {
+        self.hgg.iter()
+    }
+
+    /// Iterates over the `(node, key, value)` entries in order of their node index, allowing the values to be mutated.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &K, &mut V)> + '_ {
+        self.hgg.iter_mut()
+    }
+
+    /// Iterates over the keys in order of their node index.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.hgg.iter().map(|(_, key, _)| key)
+    }
+
+    /// Iterates over the values in order of their node index.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.hgg.iter().map(|(_, _, value)| value)
+    }
+
+    /// Inserts a (key, value) pair like [`KnnInsert::insert`], and counts the work done by the searches for its neighbors.
+    ///
+    /// This includes the greedy search down to the bottom layer and the kNN search on every layer the node is added to,
+    /// but not the freshening that follows.
+    ///
+    /// Returns the index of the new node along with the counters.
+    pub fn insert_with_stats(&mut self, key: K, value: V) -> (usize, SearchStats) {
+        let mut stats = SearchStats::default();
+        let node = self.hgg.insert_visit(
+            &mut Scratch::with_visited(self.hgg.visited),
+            key,
+            value,
+            &mut stats,
+        );
+        (node, stats)
+    }
+
+    /// Inserts a (key, value) pair like [`KnnInsert::insert`], using the scratch space of `ctx`.
+    ///
+    /// Reusing one [`SearchContext`] for every insertion avoids allocating scratch space for each one.
+    /// The searches keep track of the nodes they visit as chosen by [`Self::visited`].
+    ///
+    /// Returns the index of the new node.
+    pub fn insert_in(
+        &mut self,
+        ctx: &mut SearchContext<K, M::Unit, Ix>,
+        key: K,
+        value: V,
+    ) -> usize {
+        let visited = self.hgg.visited;
+        self.hgg
+            .insert_visit(ctx.hgg_scratch(visited), key, value, &mut ())
+    }
+
+    /// Inserts a batch of (key, value) pairs.
+    ///
+    /// The entries are inserted in chunks. The bottom layer neighbors of every key in a chunk are searched for
+    /// at once, and then the keys are connected to them and to each other with the same guarantees as
+    /// [`KnnInsert::insert`]. Freshening is deferred until the end of each chunk, where the graph is freshened
+    /// as many times as it would be by inserting each key individually.
+    /// Since the searches can't find the other keys of their chunk, the distance between every pair of keys
+    /// in a chunk is also computed, which is quadratic in the chunk length of 256.
+    /// With the `rayon` feature, use `par_insert_batch` to search for the neighbors in parallel.
+    ///
+    /// Returns the range of indices of the inserted nodes.
+    pub fn insert_batch(&mut self, entries: impl IntoIterator<Item = (K, V)>) -> Range<usize> {
+        self.hgg.insert_batch(entries)
+    }
+
+    /// Changes the key of a node. Its index and value stay the same.
+    ///
+    /// The node is disconnected from every layer it exists on just like [`Self::remove`], and then it is
+    /// connected again at the location of its new key. Only the neighborhoods around its old and new
+    /// locations are optimized, so this is much cheaper than rebuilding the graph.
+    ///
+    /// Returns the old key, or `None` if the node does not exist.
+    pub fn update_key(&mut self, node: usize, key: K) -> Option<K> {
+        self.hgg.update_key(node, key)
+    }
+
+    /// Removes a node from the graph, returning its (key, value) pair.
+    ///
+    /// The node is disconnected from every layer it exists on, and the graph is repaired so that
+    /// its former neighbors can still reach each other by greedy search.
+    ///
+    /// Just like [`Vec::swap_remove`], the last node is moved into the index of the removed node.
+    /// Any index you are holding for the last node is now `node` instead.
+    ///
+    /// Besides repairing the graph, this takes time linear in the number of nodes to find the nodes before the
+    /// removed and moved nodes in the freshening order, so removing many nodes one at a time is quadratic.
+    ///
+    /// Returns `None` if the node does not exist.
+    pub fn remove(&mut self, node: usize) -> Option<(K, V)> {
+        self.hgg.remove(node)
+    }
+
+    /// Marks a node as deleted without removing it from the graph.
+    ///
+    /// The node is still used to route searches, but it no longer appears in the results of [`Knn::knn`]
+    /// or anything built on top of it, such as [`Knn::nn`] and [`KnnPoints::knn_points`].
+    /// This is much cheaper than [`Hgg::remove`]. Call [`Hgg::compact`] later to physically remove
+    /// all the nodes marked as deleted at once.
+    ///
+    /// Searches skip deleted nodes like a filter, so they take longer as more nodes are deleted, and they search
+    /// the whole graph once fewer nodes are left than they return (see [`Self::knn_filtered`]).
+    ///
+    /// Returns `false` if the node does not exist or was already marked as deleted.
+    pub fn mark_deleted(&mut self, node: usize) -> bool {
+        self.hgg.mark_deleted(node)
+    }
+
+    /// Checks if a node is marked as deleted.
+    ///
+    /// Returns `false` if the node does not exist.
+    pub fn is_deleted(&self, node: usize) -> bool {
+        self.hgg.is_deleted(node)
+    }
+
+    /// Returns the number of nodes marked as deleted.
+    pub fn deleted_len(&self) -> usize {
+        self.hgg.deleted_len()
+    }
+
+    /// Physically removes all nodes marked as deleted from the graph.
+    ///
+    /// Only the neighborhoods around the deleted nodes are repaired. The remaining nodes keep
+    /// their relative order, but they are renumbered to fill in the gaps.
+    ///
+    /// Returns a map from the old index of every node to its new index, or `None` if it was removed.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        self.hgg.compact()
+    }
+
     /// Checks if the graph is empty.
     pub fn is_empty(&self) -> bool {
         self.hgg.is_empty()
     }
 
-    /// Returns the number of (key, value) pairs added to the graph.
+    /// Returns the number of (key, value) pairs added to the graph, including nodes marked as deleted.
     pub fn len(&self) -> usize {
         self.hgg.len()
     }
@@ -227,15 +705,95 @@ where
     pub fn simple_representation(&self) -> Vec<Vec<Vec<usize>>> {
         self.hgg.simple_representation()
     }
+
+    /// Returns the sizes of the connected components of each layer, from largest to smallest.
+    ///
+    /// Every layer of a healthy graph has a single component containing every node on the layer.
+    pub fn layer_components(&self) -> Vec<Vec<usize>> {
+        self.hgg.layer_components()
+    }
+
+    /// Returns every node which [`Self::greedy`] can't find when searching for its own key, from lowest to highest.
+    ///
+    /// Finding another node with an identical key (a distance of zero) counts as finding the node.
+    /// The number of unreachable nodes divided by [`Self::len`] is the greedy self-recall miss rate.
+    /// This performs a greedy search for every node, so it takes time linear in the size of the graph.
+    pub fn unreachable(&self) -> Vec<usize> {
+        self.hgg.unreachable()
+    }
+
+    /// Returns every node below the top layer which has no neighbor on its highest layer that is also on the layer above.
+    ///
+    /// Greedy search can only leave such a node by moving down, so it may get stuck there. Insertion raises new
+    /// nodes until they have such a neighbor, but freshening can remove it again, so this isn't an invariant
+    /// checked by [`Self::validate`]. It takes time linear in the number of edges.
+    pub fn missing_upper_neighbors(&self) -> Vec<usize> {
+        self.hgg.missing_upper_neighbors()
+    }
+
+    /// Checks that the internal invariants of the graph hold, returning the first one found to be broken.
+    ///
+    /// This checks that edges are symmetric, that cached keys match the keys of their nodes,
+    /// that the recorded counts of edges and nodes are accurate, that the freshening order is a single cycle,
+    /// and that the root is alone on the top layer. It doesn't check that every node can reach the layer above it,
+    /// since freshening doesn't maintain that; see [`Self::missing_upper_neighbors`].
+    /// It takes time linear in the number of edges, so it is intended for debugging and for checking
+    /// a graph after deserialization.
+    pub fn validate(&self) -> Result<(), HggInvariantError>
+    where
+        K: PartialEq,
+    {
+        self.hgg.validate(|cached, key| cached == key)
+    }
 }
 
-impl<M, K, V> Default for Hgg<M, K, V>
+impl<M, K, V, Ix> Default for Hgg<M, K, V, Ix>
 where
     M: Metric<K> + Default,
     K: Clone,
+    Ix: NodeIndex,
 {
     fn default() -> Self {
-        Self::new(M::default())
+        Self::with_node_index(M::default())
+    }
+}
+
+impl<M, K, V, Ix> iter::FromIterator<(K, V)> for Hgg<M, K, V, Ix>
+where
+    M: Metric<K> + Default,
+    K: Clone,
+    Ix: NodeIndex,
+{
+    /// Creates a new [`Hgg`] with default settings and inserts the (key, value) pairs using [`Hgg::insert_batch`].
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut hgg = Self::with_node_index(M::default());
+        hgg.insert_batch(iter);
+        hgg
+    }
+}
+
+impl<M, K, V, Ix> Extend<(K, V)> for Hgg<M, K, V, Ix>
+where
+    M: Metric<K>,
+    K: Clone,
+    Ix: NodeIndex,
+{
+    /// Inserts the (key, value) pairs using [`Hgg::insert_batch`].
+    ///
+    /// The graph is freshened as many times as it would be by inserting each pair individually, so this does
+    /// not save any freshening work.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.insert_batch(iter);
+    }
+}
+
+impl<M, K, V, Ix: NodeIndex> IntoIterator for Hgg<M, K, V, Ix> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Moves the (key, value) pairs out of the graph in order of their node index, including the nodes marked as deleted.
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter(self.hgg.into_entries().into_iter())
     }
 }
 
@@ -252,23 +810,32 @@ where
 ///
 /// Always remember to benchmark rather than guess when it comes to the above choices.
 ///
+/// [`HggLite`] is [`Send`] and [`Sync`] when its metric, keys, and values are, so it can be shared between threads
+/// in an `Arc` and searched concurrently. With the `rayon` feature enabled, `knn_batch` and `nn_batch`
+/// search many queries in parallel.
+///
+/// With the `serde` feature enabled, it is serialized with a header containing a format version and checksum,
+/// and it is checked for corruption when it is deserialized. The checksum only covers the structure of the graph
+/// and its counts, not the keys, the values, or the `visited` and `repair_unreachable` settings, so corruption
+/// of those is only caught if they can't be deserialized. The indices are written as `Ix`, so a graph must be
+/// deserialized with the same [`NodeIndex`] type that it was serialized with.
+///
+/// With the `zerocopy` feature enabled, `to_view_bytes` writes it in a flat layout that can be memory-mapped
+/// and searched with `HggView` without deserializing it.
+///
+/// The indices of nodes are stored as `Ix`, which is `usize` by default. Use [`HggLite::with_node_index`] to store
+/// them as a smaller [`NodeIndex`] type, such as `u32`, when the graph will never hold that many nodes.
+///
 /// If you are looking for how to perform kNN searches, see `impl<K, V> Knn<K> for HggLite<K, V>` below.
-#[derive(Debug)]
-#[cfg_attr(
-    feature = "serde",
-    derive(Serialize, Deserialize),
-    serde(bound(
-        serialize = "M: Serialize, K: Serialize, V: Serialize",
-        deserialize = "M: Deserialize<'de>, K: Deserialize<'de>, V: Deserialize<'de>"
-    ))
-)]
-pub struct HggLite<M, K, V> {
-    hgg: HggCore<M, K, V, (), StrategyLite>,
+#[derive(Debug, Clone)]
+pub struct HggLite<M, K, V, Ix: NodeIndex = usize> {
+    hgg: HggCore<M, K, V, (), StrategyLite, Ix>,
 }
 
-impl<M, K, V> Knn for HggLite<M, K, V>
+impl<M, K, V, Ix> Knn for HggLite<M, K, V, Ix>
 where
     M: Metric<K>,
+    Ix: NodeIndex,
 {
     type Ix = usize;
     type Metric = M;
@@ -277,24 +844,33 @@ where
 
     fn knn(&self, query: &K, num: usize) -> Self::KnnIter {
         self.hgg
-            .search_knn(query, num)
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                &mut (),
+            )
+            .into_iter()
             .map(|(index, distance)| Neighbor { index, distance })
             .collect()
     }
 }
 
-impl<M, K, V> KnnPoints for HggLite<M, K, V>
+impl<M, K, V, Ix> KnnPoints for HggLite<M, K, V, Ix>
 where
     M: Metric<K>,
+    Ix: NodeIndex,
 {
     fn get_point(&self, index: usize) -> &'_ K {
         self.get_key(index).unwrap()
     }
 }
 
-impl<M, K, V> KnnMap for HggLite<M, K, V>
+impl<M, K, V, Ix> KnnMap for HggLite<M, K, V, Ix>
 where
     M: Metric<K>,
+    Ix: NodeIndex,
 {
     type Value = V;
     fn get_value(&self, index: usize) -> &'_ V {
@@ -302,9 +878,10 @@ where
     }
 }
 
-impl<M, K, V> KnnInsert for HggLite<M, K, V>
+impl<M, K, V, Ix> KnnInsert for HggLite<M, K, V, Ix>
 where
     M: Metric<K>,
+    Ix: NodeIndex,
 {
     fn insert(&mut self, key: Self::Point, value: Self::Value) -> usize {
         self.hgg.insert(key, value)
@@ -322,14 +899,40 @@ where
         }
     }
 
-    /// Default value: `16`
-    ///
-    /// Decrease this to speed up at the expense of recall, and vice versa.
+    /// Creates a new [`HggLite`] with default settings from a batch of (key, value) pairs.
     ///
-    /// The number of nearest neighbors to add on top of the number requested to increase recall.
-    pub fn search_extra_knn(self, search_extra_knn: usize) -> Self {
-        Self {
-            hgg: self.hgg.search_extra_knn(search_extra_knn),
+    /// See [`HggLite::insert_batch`] for details. The nodes are numbered in the order of `entries`.
+    pub fn from_batch(metric: M, entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut hgg = Self::new(metric);
+        hgg.insert_batch(entries);
+        hgg
+    }
+}
+
+impl<M, K, V, Ix> HggLite<M, K, V, Ix>
+where
+    M: Metric<K>,
+    Ix: NodeIndex,
+{
+    /// Creates a new [`HggLite`] which stores the indices of its nodes as `Ix` instead of `usize`.
+    /// It will be empty and begin with default settings.
+    ///
+    /// See [`NodeIndex`] for details.
+    pub fn with_node_index(metric: M) -> Self {
+        Self {
+            hgg: HggCore::new(metric),
+        }
+    }
+
+    /// Default value: `16`
+    ///
+    /// Decrease this to speed up at the expense of recall, and vice versa.
+    ///
+    /// The number of nearest neighbors to add on top of the number requested to increase recall.
+    /// This is also the number of nodes outside of the radius that are searched through in a radius search.
+    pub fn search_extra_knn(self, search_extra_knn: usize) -> Self {
+        Self {
+            hgg: self.hgg.search_extra_knn(search_extra_knn),
         }
     }
 
@@ -358,6 +961,19 @@ where
         }
     }
 
+    /// Default value: [`VisitedSet::Hash`]
+    ///
+    /// Chooses how searches keep track of the nodes they have already added to their pool of candidates.
+    /// [`VisitedSet::Epoch`] needs a slot for every node, which searches that don't reuse their scratch space
+    /// have to allocate every time, so it works best with [`Self::knn_in`], [`Self::insert_in`] and `knn_batch`.
+    /// It replaces hashing with an array access, which is faster on large graphs.
+    /// The `visited` example compares the two.
+    pub fn visited(self, visited: VisitedSet) -> Self {
+        Self {
+            hgg: self.hgg.visited(visited),
+        }
+    }
+
     /// Default value: `64`
     ///
     /// This controls the number of nearest neighbors used during insertion. Setting this higher will cause the graph
@@ -374,16 +990,259 @@ where
         }
     }
 
+    /// Default value: `false`
+    ///
+    /// If this is true, every node that is freshened is also checked to see if greedy search from the root
+    /// finds it when searching for its own key (see [`Self::unreachable`]). If it doesn't, the node is disconnected
+    /// and inserted again. This makes freshening slower, so only enable it if greedy self-recall matters to you.
+    pub fn repair_unreachable(self, repair_unreachable: bool) -> Self {
+        Self {
+            hgg: self.hgg.repair_unreachable(repair_unreachable),
+        }
+    }
+
+    /// Sets [`HggLite::search_extra_knn`] without consuming the index.
+    pub fn set_search_extra_knn(&mut self, search_extra_knn: usize) {
+        self.hgg.set_search_extra_knn(search_extra_knn);
+    }
+
+    /// Sets [`HggLite::freshens`] without consuming the index.
+    pub fn set_freshens(&mut self, freshens: usize) {
+        self.hgg.set_freshens(freshens);
+    }
+
+    /// Sets [`HggLite::exclude_all_searched`] without consuming the index.
+    pub fn set_exclude_all_searched(&mut self, exclude_all_searched: bool) {
+        self.hgg.set_exclude_all_searched(exclude_all_searched);
+    }
+
+    /// Sets [`HggLite::visited`] without consuming the index.
+    pub fn set_visited(&mut self, visited: VisitedSet) {
+        self.hgg.set_visited(visited);
+    }
+
+    /// Sets [`HggLite::insert_knn`] without consuming the index.
+    pub fn set_insert_knn(&mut self, insert_knn: usize) {
+        self.hgg.set_insert_knn(insert_knn);
+    }
+
+    /// Sets [`HggLite::repair_unreachable`] without consuming the index.
+    pub fn set_repair_unreachable(&mut self, repair_unreachable: bool) {
+        self.hgg.set_repair_unreachable(repair_unreachable);
+    }
+
+    /// Returns the parameters used by [`Knn::knn`].
+    pub fn search_params(&self) -> SearchParams {
+        self.hgg.search_params()
+    }
+
+    /// Sets the parameters used by [`Knn::knn`].
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.hgg.set_search_params(params);
+    }
+
+    /// Finds the `num` nearest neighbors of the query using the given search parameters
+    /// instead of the ones stored in the index.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_with(&self, query: &K, num: usize, params: &SearchParams) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                params,
+                &mut (),
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds the `num` nearest neighbors of the query like [`Knn::knn`], using the scratch space of `ctx`.
+    ///
+    /// Reusing one [`SearchContext`] for every search on a thread avoids allocating scratch space for each search.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_in(
+        &self,
+        ctx: &mut SearchContext<K, M::Unit, Ix>,
+        query: &K,
+        num: usize,
+    ) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn(
+                ctx.lite_scratch(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                &mut (),
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds the `num` nearest neighbors of the query, calling the `visitor` on every step of the search.
+    ///
+    /// The search is exactly the same as [`Knn::knn`], so this can be used to trace the path taken by a query
+    /// that returns bad results.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_visit(
+        &self,
+        query: &K,
+        num: usize,
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                visitor,
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds the `num` nearest neighbors of the query like [`Self::knn_with`], and counts the work done by the search.
+    ///
+    /// This is useful for tuning the search parameters and [`Self::insert_knn`], especially with expensive metrics.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest along with the counters.
+    pub fn knn_with_stats(
+        &self,
+        query: &K,
+        num: usize,
+        params: &SearchParams,
+    ) -> (Vec<Neighbor<M::Unit>>, SearchStats) {
+        let mut stats = SearchStats::default();
+        let neighbors = self
+            .hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                params,
+                &mut stats,
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect();
+        (neighbors, stats)
+    }
+
+    /// Finds the `num` nearest neighbors of the query like [`Knn::knn`], but stops early once the `budget` runs out.
+    ///
+    /// This bounds the work done by a search, which is useful when there is a hard limit on latency.
+    ///
+    /// The limits also apply to the greedy search through the upper layers, so a search which stops there only
+    /// returns the node it reached, or nothing if that node is marked as deleted. Only nodes which aren't marked
+    /// as deleted count towards [`SearchBudget::good_enough`].
+    ///
+    /// Returns the best neighbors found so far sorted from nearest to furthest, along with how the search ended.
+    pub fn knn_with_budget(
+        &self,
+        query: &K,
+        num: usize,
+        budget: &SearchBudget<M::Unit>,
+    ) -> (Vec<Neighbor<M::Unit>>, BudgetOutcome) {
+        let mut visitor = BudgetVisitor::new(*budget, |node: usize| !self.hgg.nodes[node].deleted);
+        let neighbors = self
+            .hgg
+            .search_knn(
+                &mut Scratch::with_visited(self.hgg.visited),
+                query,
+                num,
+                &self.hgg.search_params(),
+                &mut visitor,
+            )
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect();
+        (neighbors, visitor.outcome)
+    }
+
+    /// Finds the nearest neighbors of the query lazily, searching further as more neighbors are taken.
+    ///
+    /// This is useful when the number of neighbors needed isn't known beforehand, such as when looking for the
+    /// nearest neighbor which matches some condition. The search keeps [`Self::search_extra_knn`] extra nodes
+    /// ahead of the neighbors it returns, like [`Knn::knn`]. Nodes marked as deleted are not returned.
+    ///
+    /// Returns the neighbors from nearest to furthest, except that a node found only after a further neighbor
+    /// was returned is still returned, just out of order. Each neighbor returned is the nearest of the nodes found
+    /// so far, so once [`Self::search_extra_knn`] is at least the number of nodes, the whole graph is found before
+    /// the first neighbor is returned and the order is exact. Iterating to the end returns every node which is not
+    /// marked as deleted.
+    pub fn knn_iter<'a>(&'a self, query: &'a K) -> impl Iterator<Item = Neighbor<M::Unit>> + 'a {
+        KnnIter::new(&self.hgg, query, &self.hgg.search_params(), move |node| {
+            !self.hgg.nodes[node].deleted
+        })
+        .map(|(index, distance)| Neighbor { index, distance })
+    }
+
     /// Searches for the nearest neighbor greedily from the top layer to the bottom.
     ///
     /// This does not utilize [`Self::search_extra_knn`]. It is specialized to search greedily
-    /// keeping track of only one best node at a time.
+    /// keeping track of only one best node at a time. It may return a node marked as deleted.
     ///
     /// Returns `(node, distance)`.
     pub fn greedy(&self, query: &K) -> Option<(usize, M::Unit)> {
         self.hgg.search(query)
     }
 
+    /// Finds the `num` nearest neighbors of the query which pass the `filter`.
+    ///
+    /// The filter is given the index, key, and value of each node. Nodes that don't pass the filter are still
+    /// searched through so that the graph remains navigable, but they never appear in the results. This has much
+    /// better recall than filtering the results of [`Knn::knn`]. Nodes marked as deleted are not returned.
+    ///
+    /// Every node that is searched through is kept until `num` nodes have passed the filter, and there is no limit
+    /// on how many that can be. If few nodes near the query pass the filter, the search grows until it finds them,
+    /// and if fewer than `num` nodes pass at all, it searches the whole graph. When a filter rejects most of the
+    /// graph, it can be faster to search the accepted nodes directly.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_filtered(
+        &self,
+        query: &K,
+        num: usize,
+        filter: impl Fn(usize, &K, &V) -> bool,
+    ) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_knn_filtered_entries(query, num, filter)
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Finds every node within `radius` of the query.
+    ///
+    /// This is [`Self::within_with`] with a `slack` of [`Self::search_extra_knn`].
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn within(&self, query: &K, radius: M::Unit) -> Vec<Neighbor<M::Unit>> {
+        self.within_with(query, radius, self.hgg.search_extra_knn)
+    }
+
+    /// Finds every node within `radius` of the query, searching through up to `slack` nodes outside of it.
+    ///
+    /// Every node found within the radius is searched through, along with up to `slack` of the nearest nodes
+    /// found outside of it, since some nodes within the radius may only be connected through nodes outside of it.
+    /// Increase `slack` to improve recall at the expense of speed. Nodes marked as deleted are not returned.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn within_with(&self, query: &K, radius: M::Unit, slack: usize) -> Vec<Neighbor<M::Unit>> {
+        self.hgg
+            .search_within(query, radius, slack)
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
     /// Get the (key, value) pair of a node.
     pub fn get(&self, node: usize) -> Option<(&K, &V)> {
         self.hgg.get(node)
@@ -399,12 +1258,160 @@ where
         self.hgg.get_value(node)
     }
 
+    /// Get a mutable reference to the value of a node.
+    ///
+    /// The key can't be mutated this way, as the graph depends on it. Use [`Self::update_key`] instead.
+    pub fn get_value_mut(&mut self, node: usize) -> Option<&mut V> {
+        self.hgg.get_value_mut(node)
+    }
+
+    /// Iterates over the `(node, key, value)` entries in order of their node index.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &K, &V)> + '_ {
+        self.hgg.iter()
+    }
+
+    /// Iterates over the `(node, key, value)` entries in order of their node index, allowing the values to be mutated.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &K, &mut V)> + '_ {
+        self.hgg.iter_mut()
+    }
+
+    /// Iterates over the keys in order of their node index.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.hgg.iter().map(|(_, key, _)| key)
+    }
+
+    /// Iterates over the values in order of their node index.
+    ///
+    /// This includes the nodes marked as deleted.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.hgg.iter().map(|(_, _, value)| value)
+    }
+
+    /// Inserts a (key, value) pair like [`KnnInsert::insert`], and counts the work done by the searches for its neighbors.
+    ///
+    /// This includes the greedy search down to the bottom layer and the kNN search on every layer the node is added to,
+    /// but not the freshening that follows.
+    ///
+    /// Returns the index of the new node along with the counters.
+    pub fn insert_with_stats(&mut self, key: K, value: V) -> (usize, SearchStats) {
+        let mut stats = SearchStats::default();
+        let node = self.hgg.insert_visit(
+            &mut Scratch::with_visited(self.hgg.visited),
+            key,
+            value,
+            &mut stats,
+        );
+        (node, stats)
+    }
+
+    /// Inserts a (key, value) pair like [`KnnInsert::insert`], using the scratch space of `ctx`.
+    ///
+    /// Reusing one [`SearchContext`] for every insertion avoids allocating scratch space for each one.
+    /// The searches keep track of the nodes they visit as chosen by [`Self::visited`].
+    ///
+    /// Returns the index of the new node.
+    pub fn insert_in(
+        &mut self,
+        ctx: &mut SearchContext<K, M::Unit, Ix>,
+        key: K,
+        value: V,
+    ) -> usize {
+        let visited = self.hgg.visited;
+        self.hgg
+            .insert_visit(ctx.lite_scratch(visited), key, value, &mut ())
+    }
+
+    /// Inserts a batch of (key, value) pairs.
+    ///
+    /// The entries are inserted in chunks. The bottom layer neighbors of every key in a chunk are searched for
+    /// at once, and then the keys are connected to them and to each other with the same guarantees as
+    /// [`KnnInsert::insert`]. Freshening is deferred until the end of each chunk, where the graph is freshened
+    /// as many times as it would be by inserting each key individually.
+    /// Since the searches can't find the other keys of their chunk, the distance between every pair of keys
+    /// in a chunk is also computed, which is quadratic in the chunk length of 256.
+    /// With the `rayon` feature, use `par_insert_batch` to search for the neighbors in parallel.
+    ///
+    /// Returns the range of indices of the inserted nodes.
+    pub fn insert_batch(&mut self, entries: impl IntoIterator<Item = (K, V)>) -> Range<usize> {
+        self.hgg.insert_batch(entries)
+    }
+
+    /// Changes the key of a node. Its index and value stay the same.
+    ///
+    /// The node is disconnected from every layer it exists on just like [`Self::remove`], and then it is
+    /// connected again at the location of its new key. Only the neighborhoods around its old and new
+    /// locations are optimized, so this is much cheaper than rebuilding the graph.
+    ///
+    /// Returns the old key, or `None` if the node does not exist.
+    pub fn update_key(&mut self, node: usize, key: K) -> Option<K> {
+        self.hgg.update_key(node, key)
+    }
+
+    /// Removes a node from the graph, returning its (key, value) pair.
+    ///
+    /// The node is disconnected from every layer it exists on, and the graph is repaired so that
+    /// its former neighbors can still reach each other by greedy search.
+    ///
+    /// Just like [`Vec::swap_remove`], the last node is moved into the index of the removed node.
+    /// Any index you are holding for the last node is now `node` instead.
+    ///
+    /// Besides repairing the graph, this takes time linear in the number of nodes to find the nodes before the
+    /// removed and moved nodes in the freshening order, so removing many nodes one at a time is quadratic.
+    ///
+    /// Returns `None` if the node does not exist.
+    pub fn remove(&mut self, node: usize) -> Option<(K, V)> {
+        self.hgg.remove(node)
+    }
+
+    /// Marks a node as deleted without removing it from the graph.
+    ///
+    /// The node is still used to route searches, but it no longer appears in the results of [`Knn::knn`]
+    /// or anything built on top of it, such as [`Knn::nn`] and [`KnnPoints::knn_points`].
+    /// This is much cheaper than [`HggLite::remove`]. Call [`HggLite::compact`] later to physically remove
+    /// all the nodes marked as deleted at once.
+    ///
+    /// Searches skip deleted nodes like a filter, so they take longer as more nodes are deleted, and they search
+    /// the whole graph once fewer nodes are left than they return (see [`Self::knn_filtered`]).
+    ///
+    /// Returns `false` if the node does not exist or was already marked as deleted.
+    pub fn mark_deleted(&mut self, node: usize) -> bool {
+        self.hgg.mark_deleted(node)
+    }
+
+    /// Checks if a node is marked as deleted.
+    ///
+    /// Returns `false` if the node does not exist.
+    pub fn is_deleted(&self, node: usize) -> bool {
+        self.hgg.is_deleted(node)
+    }
+
+    /// Returns the number of nodes marked as deleted.
+    pub fn deleted_len(&self) -> usize {
+        self.hgg.deleted_len()
+    }
+
+    /// Physically removes all nodes marked as deleted from the graph.
+    ///
+    /// Only the neighborhoods around the deleted nodes are repaired. The remaining nodes keep
+    /// their relative order, but they are renumbered to fill in the gaps.
+    ///
+    /// Returns a map from the old index of every node to its new index, or `None` if it was removed.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        self.hgg.compact()
+    }
+
     /// Checks if the graph is empty.
     pub fn is_empty(&self) -> bool {
         self.hgg.is_empty()
     }
 
-    /// Returns the number of (key, value) pairs added to the graph.
+    /// Returns the number of (key, value) pairs added to the graph, including nodes marked as deleted.
     pub fn len(&self) -> usize {
         self.hgg.len()
     }
@@ -434,100 +1441,658 @@ where
     pub fn simple_representation(&self) -> Vec<Vec<Vec<usize>>> {
         self.hgg.simple_representation()
     }
+
+    /// Returns the sizes of the connected components of each layer, from largest to smallest.
+    ///
+    /// Every layer of a healthy graph has a single component containing every node on the layer.
+    pub fn layer_components(&self) -> Vec<Vec<usize>> {
+        self.hgg.layer_components()
+    }
+
+    /// Returns every node which [`Self::greedy`] can't find when searching for its own key, from lowest to highest.
+    ///
+    /// Finding another node with an identical key (a distance of zero) counts as finding the node.
+    /// The number of unreachable nodes divided by [`Self::len`] is the greedy self-recall miss rate.
+    /// This performs a greedy search for every node, so it takes time linear in the size of the graph.
+    pub fn unreachable(&self) -> Vec<usize> {
+        self.hgg.unreachable()
+    }
+
+    /// Returns every node below the top layer which has no neighbor on its highest layer that is also on the layer above.
+    ///
+    /// Greedy search can only leave such a node by moving down, so it may get stuck there. Insertion raises new
+    /// nodes until they have such a neighbor, but freshening can remove it again, so this isn't an invariant
+    /// checked by [`Self::validate`]. It takes time linear in the number of edges.
+    pub fn missing_upper_neighbors(&self) -> Vec<usize> {
+        self.hgg.missing_upper_neighbors()
+    }
+
+    /// Checks that the internal invariants of the graph hold, returning the first one found to be broken.
+    ///
+    /// This checks that edges are symmetric, that the recorded counts of edges and nodes are accurate,
+    /// that the freshening order is a single cycle, and that the root is alone on the top layer.
+    /// It doesn't check that every node can reach the layer above it, since freshening doesn't maintain that;
+    /// see [`Self::missing_upper_neighbors`].
+    /// It takes time linear in the number of edges, so it is intended for debugging and for checking
+    /// a graph after deserialization.
+    pub fn validate(&self) -> Result<(), HggInvariantError> {
+        self.hgg.validate(|_, _| true)
+    }
 }
 
-impl<M, K, V> Default for HggLite<M, K, V>
+impl<M, K, V, Ix> Default for HggLite<M, K, V, Ix>
 where
     M: Metric<K> + Default,
+    Ix: NodeIndex,
 {
     fn default() -> Self {
-        Self::new(M::default())
+        Self::with_node_index(M::default())
+    }
+}
+
+impl<M, K, V, Ix> iter::FromIterator<(K, V)> for HggLite<M, K, V, Ix>
+where
+    M: Metric<K> + Default,
+    Ix: NodeIndex,
+{
+    /// Creates a new [`HggLite`] with default settings and inserts the (key, value) pairs using [`HggLite::insert_batch`].
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut hgg = Self::with_node_index(M::default());
+        hgg.insert_batch(iter);
+        hgg
     }
 }
 
+impl<M, K, V, Ix> Extend<(K, V)> for HggLite<M, K, V, Ix>
+where
+    M: Metric<K>,
+    Ix: NodeIndex,
+{
+    /// Inserts the (key, value) pairs using [`HggLite::insert_batch`].
+    ///
+    /// The graph is freshened as many times as it would be by inserting each pair individually, so this does
+    /// not save any freshening work.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.insert_batch(iter);
+    }
+}
+
+impl<M, K, V, Ix: NodeIndex> IntoIterator for HggLite<M, K, V, Ix> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Moves the (key, value) pairs out of the graph in order of their node index, including the nodes marked as deleted.
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter(self.hgg.into_entries().into_iter())
+    }
+}
+
+/// An iterator that moves the (key, value) pairs out of an [`Hgg`] or [`HggLite`] in order of their node index.
+///
+/// The edges of the graph are freed when it is created.
 #[derive(Debug)]
-struct HggNode<K, V, HK> {
+pub struct IntoIter<K, V>(vec::IntoIter<(K, V)>);
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        self.0.next_back()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+/// Parameters which control the tradeoff between recall and speed of a single kNN search.
+///
+/// Every [`Hgg`] and [`HggLite`] has its own parameters which are used by [`Knn::knn`]. You can
+/// get them with [`Hgg::search_params`], modify them, and pass them to [`Hgg::knn_with`] to search
+/// at a different point on the recall curve without modifying the index itself. This allows
+/// different threads to search one shared index with different parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SearchParams {
+    /// Default value: `16`
+    ///
+    /// See [`Hgg::search_extra_knn`].
+    pub search_extra_knn: usize,
+    /// Default value: `false`
+    ///
+    /// See [`Hgg::exclude_all_searched`].
+    pub exclude_all_searched: bool,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self {
+            search_extra_knn: 16,
+            exclude_all_searched: false,
+        }
+    }
+}
+
+/// How kNN search keeps track of the nodes which it has already added to its pool of candidates.
+///
+/// See [`Hgg::visited`] and [`HggLite::visited`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VisitedSet {
+    /// Nodes are kept in a hash set, which only grows as large as the pool of candidates.
+    #[default]
+    Hash,
+    /// Every node in the graph has a slot in an array, which holds the number of the last search that visited it.
+    ///
+    /// Checking a node is a single array access, but the array is as long as the graph, so it is best kept in a
+    /// [`SearchContext`] between searches.
+    Epoch,
+}
+
+/// Counters of the work done by a search, returned by [`Hgg::knn_with_stats`] and [`Hgg::insert_with_stats`].
+///
+/// This is a [`SearchVisitor`], so it can also be passed to [`Hgg::knn_visit`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The number of times the distance to the query was computed.
+    pub distance_evaluations: usize,
+    /// The number of nodes whose neighbors were searched by kNN search.
+    pub expansions: usize,
+    /// The number of times greedy search moved to a closer neighbor on each layer, starting from the bottom layer.
+    pub greedy_hops: Vec<usize>,
+    /// The number of nodes in the pools of the kNN searches when they finished, including nodes
+    /// that were filtered out or exceeded the requested number of neighbors.
+    pub pool_size: usize,
+    /// The layer the search is currently on.
+    layer: usize,
+}
+
+impl<Unit> SearchVisitor<Unit> for SearchStats {
+    fn enter_layer(&mut self, layer: usize) {
+        if self.greedy_hops.len() <= layer {
+            self.greedy_hops.resize(layer + 1, 0);
+        }
+        self.layer = layer;
+    }
+
+    fn distance(&mut self, _node: usize, _distance: Unit) {
+        self.distance_evaluations += 1;
+    }
+
+    fn hop(&mut self, _node: usize, _distance: Unit) {
+        self.greedy_hops[self.layer] += 1;
+    }
+
+    fn expand(&mut self, _node: usize) {
+        self.expansions += 1;
+    }
+
+    fn pool_finish(&mut self, size: usize) {
+        self.pool_size += size;
+    }
+}
+
+/// Limits on the work done by a search, passed to [`Hgg::knn_with_budget`].
+///
+/// The search stops as soon as any of the limits is reached, including during the greedy search through the
+/// upper layers. A limit of `None` is never reached.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SearchBudget<Unit> {
+    /// The search stops once it has computed this many distances to the query.
+    pub max_distance_evaluations: Option<usize>,
+    /// The search stops once it has searched the neighbors of this many nodes with kNN search.
+    pub max_expansions: Option<usize>,
+    /// The search stops once it finds a node at most this far from the query.
+    pub good_enough: Option<Unit>,
+}
+
+impl<Unit> Default for SearchBudget<Unit> {
+    fn default() -> Self {
+        Self {
+            max_distance_evaluations: None,
+            max_expansions: None,
+            good_enough: None,
+        }
+    }
+}
+
+/// How a search limited by a [`SearchBudget`] ended, returned by [`Hgg::knn_with_budget`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BudgetOutcome {
+    /// The search finished without reaching any of the limits.
+    Completed,
+    /// The search stopped because it found a node within [`SearchBudget::good_enough`].
+    GoodEnough,
+    /// The search stopped because it reached [`SearchBudget::max_distance_evaluations`]
+    /// or [`SearchBudget::max_expansions`].
+    BudgetExhausted,
+}
+
+/// Stops a search once its [`SearchBudget`] runs out.
+struct BudgetVisitor<Unit, F> {
+    budget: SearchBudget<Unit>,
+    /// Decides if a node can be returned, so that only those count towards [`SearchBudget::good_enough`].
+    accept: F,
+    distance_evaluations: usize,
+    expansions: usize,
+    /// If a node within [`SearchBudget::good_enough`] was found.
+    found: bool,
+    /// Why the search was stopped, if it was.
+    outcome: BudgetOutcome,
+}
+
+impl<Unit, F> BudgetVisitor<Unit, F> {
+    fn new(budget: SearchBudget<Unit>, accept: F) -> Self {
+        Self {
+            budget,
+            accept,
+            distance_evaluations: 0,
+            expansions: 0,
+            found: false,
+            outcome: BudgetOutcome::Completed,
+        }
+    }
+}
+
+impl<Unit: PartialOrd, F: Fn(usize) -> bool> SearchVisitor<Unit> for BudgetVisitor<Unit, F> {
+    fn distance(&mut self, node: usize, distance: Unit) {
+        self.distance_evaluations += 1;
+        if let Some(good_enough) = &self.budget.good_enough {
+            self.found |= distance <= *good_enough && (self.accept)(node);
+        }
+    }
+
+    fn expand(&mut self, _node: usize) {
+        self.expansions += 1;
+    }
+
+    fn stop(&mut self) -> bool {
+        if self.outcome == BudgetOutcome::Completed {
+            if self.found {
+                self.outcome = BudgetOutcome::GoodEnough;
+            } else if self
+                .budget
+                .max_distance_evaluations
+                .is_some_and(|max| self.distance_evaluations >= max)
+                || self
+                    .budget
+                    .max_expansions
+                    .is_some_and(|max| self.expansions >= max)
+            {
+                self.outcome = BudgetOutcome::BudgetExhausted;
+            }
+        }
+        self.outcome != BudgetOutcome::Completed
+    }
+}
+
+/// A broken invariant of the graph found by [`Hgg::validate`] or [`HggLite::validate`].
+///
+/// If you encounter one of these, the graph is corrupt and searches may miss nodes or panic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HggInvariantError {
+    /// The number of layers recorded for the graph does not match the number of layers of the root.
+    LayerCountMismatch { recorded: usize, actual: usize },
+    /// The root node does not exist.
+    RootOutOfBounds { root: usize },
+    /// A node other than the root exists on the top layer.
+    RootNotAlone { node: usize },
+    /// The header of a node on a layer does not contain the index of the node.
+    HeaderNodeMismatch {
+        node: usize,
+        layer: usize,
+        header_node: usize,
+    },
+    /// The key cached in the header of a node on a layer does not match the key of the node.
+    HeaderKeyMismatch { node: usize, layer: usize },
+    /// An edge of a node does not point to a node on the same layer.
+    DanglingEdge { node: usize, layer: usize },
+    /// A node has more than one edge to the same neighbor.
+    DuplicateEdge {
+        node: usize,
+        neighbor: usize,
+        layer: usize,
+    },
+    /// A node has an edge to a neighbor, but the neighbor has no edge back.
+    AsymmetricEdge {
+        node: usize,
+        neighbor: usize,
+        layer: usize,
+    },
+    /// The key cached in an edge does not match the key of the neighbor it points to.
+    EdgeKeyMismatch {
+        node: usize,
+        neighbor: usize,
+        layer: usize,
+    },
+    /// The number of edges recorded for a layer does not match the actual number of edges.
+    EdgeCountMismatch {
+        layer: usize,
+        recorded: usize,
+        actual: usize,
+    },
+    /// The number of nodes recorded for a layer does not match the actual number of nodes.
+    NodeCountMismatch {
+        layer: usize,
+        recorded: usize,
+        actual: usize,
+    },
+    /// The number of nodes recorded as deleted does not match the actual number of deleted nodes.
+    DeletedCountMismatch { recorded: usize, actual: usize },
+    /// The freshening order is not a single cycle through every node. It breaks at `node`.
+    BrokenFresheningOrder { node: usize },
+}
+
+impl Display for HggInvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::LayerCountMismatch { recorded, actual } => write!(
+                f,
+                "graph records {} layers, but the root is on {} layers",
+                recorded, actual
+            ),
+            Self::RootOutOfBounds { root } => write!(f, "root node {} does not exist", root),
+            Self::RootNotAlone { node } => write!(
+                f,
+                "node {} is on the top layer, but it is not the root",
+                node
+            ),
+            Self::HeaderNodeMismatch {
+                node,
+                layer,
+                header_node,
+            } => write!(
+                f,
+                "node {} on layer {} has node {} in its header",
+                node, layer, header_node
+            ),
+            Self::HeaderKeyMismatch { node, layer } => write!(
+                f,
+                "node {} on layer {} has the wrong key in its header",
+                node, layer
+            ),
+            Self::DanglingEdge { node, layer } => write!(
+                f,
+                "node {} on layer {} has an edge that does not point to a node on the same layer",
+                node, layer
+            ),
+            Self::DuplicateEdge {
+                node,
+                neighbor,
+                layer,
+            } => write!(
+                f,
+                "node {} on layer {} has multiple edges to node {}",
+                node, layer, neighbor
+            ),
+            Self::AsymmetricEdge {
+                node,
+                neighbor,
+                layer,
+            } => write!(
+                f,
+                "node {} on layer {} has an edge to node {}, but not the other way around",
+                node, layer, neighbor
+            ),
+            Self::EdgeKeyMismatch {
+                node,
+                neighbor,
+                layer,
+            } => write!(
+                f,
+                "node {} on layer {} has the wrong key in its edge to node {}",
+                node, layer, neighbor
+            ),
+            Self::EdgeCountMismatch {
+                layer,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "layer {} records {} edges, but has {}",
+                layer, recorded, actual
+            ),
+            Self::NodeCountMismatch {
+                layer,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "layer {} records {} nodes, but has {}",
+                layer, recorded, actual
+            ),
+            Self::DeletedCountMismatch { recorded, actual } => write!(
+                f,
+                "graph records {} deleted nodes, but has {}",
+                recorded, actual
+            ),
+            Self::BrokenFresheningOrder { node } => {
+                write!(f, "freshening order is broken at node {}", node)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HggNode<K, V, HK, Ix> {
     key: K,
     value: V,
     /// Contains the edges of each layer of the graph on which this exists.
-    layers: Vec<HeaderVec<HggHeader<HK>, HggEdge<HK>>>,
+    layers: Vec<HeaderVec<HggHeader<HK, Ix>, HggEdge<HK, Ix>>>,
     /// Forms a linked list through the nodes that creates the freshening order.
-    next: usize,
+    next: Ix,
+    /// Marks the node as deleted, so it is only used to route searches.
+    deleted: bool,
 }
 
-impl<K, V, HK> HggNode<K, V, HK> {
+impl<K, V, HK, Ix> HggNode<K, V, HK, Ix> {
     fn layers(&self) -> usize {
         self.layers.len()
     }
 }
 
 #[derive(Debug)]
-struct NodeStorage<K, V, HK, Strategy>(Vec<HggNode<K, V, HK>>, PhantomData<Strategy>);
+struct NodeStorage<K, V, HK, Strategy, Ix>(Vec<HggNode<K, V, HK, Ix>>, PhantomData<Strategy>);
 
-impl<K, V, HK, Strategy> Deref for NodeStorage<K, V, HK, Strategy> {
-    type Target = Vec<HggNode<K, V, HK>>;
+impl<K, V, HK, Strategy, Ix> Deref for NodeStorage<K, V, HK, Strategy, Ix> {
+    type Target = Vec<HggNode<K, V, HK, Ix>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl<K, V, HK, Strategy> DerefMut for NodeStorage<K, V, HK, Strategy> {
+impl<K, V, HK, Strategy, Ix> DerefMut for NodeStorage<K, V, HK, Strategy, Ix> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
 
+impl<K, V, HK, Strategy, Ix> Clone for NodeStorage<K, V, HK, Strategy, Ix>
+where
+    K: Clone,
+    V: Clone,
+    HK: Clone,
+    Ix: NodeIndex,
+{
+    fn clone(&self) -> Self {
+        // Create all the HggNode, but do not populate the edges yet.
+        // The capacity of each layer fits all of its edges, so pushing them won't reallocate and break the weak refs.
+        let mut nodes: Vec<HggNode<K, V, HK, Ix>> = self
+            .iter()
+            .map(|node| HggNode {
+                key: node.key.clone(),
+                value: node.value.clone(),
+                layers: node
+                    .layers
+                    .iter()
+                    .map(|layer| {
+                        HeaderVec::with_capacity(
+                            cmp::max(layer.len(), 1),
+                            HggHeader {
+                                key: layer.key.clone(),
+                                node: layer.node,
+                            },
+                        )
+                    })
+                    .collect(),
+                next: node.next,
+                deleted: node.deleted,
+            })
+            .collect();
+
+        let node_layers: Vec<Vec<HVec<HK, Ix>>> = nodes
+            .iter()
+            .map(|node| {
+                node.layers
+                    .iter()
+                    .map(|layer| HVec(unsafe { layer.weak() }))
+                    .collect()
+            })
+            .collect();
+
+        // Populate the edges, pointing them at the new layers by node index.
+        for (node, old_node) in nodes.iter_mut().zip(self.iter()) {
+            for (layer, (edges, old_edges)) in
+                node.layers.iter_mut().zip(&old_node.layers).enumerate()
+            {
+                for HggEdge { key, neighbor } in old_edges.as_slice() {
+                    edges.push(HggEdge {
+                        key: key.clone(),
+                        neighbor: node_layers[neighbor.node.index()][layer].weak(),
+                    });
+                }
+            }
+        }
+
+        NodeStorage(nodes, PhantomData)
+    }
+}
+
 /// Collection for retrieving entries based on key proximity in a metric space.
 #[derive(Debug)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
     serde(bound(
-        serialize = "M: Serialize, K: Serialize, V: Serialize, NodeStorage<K, V, HK, Strategy>: Serialize",
-        deserialize = "M: Deserialize<'de>, K: Deserialize<'de>, V: Deserialize<'de>, NodeStorage<K, V, HK, Strategy>: Deserialize<'de>"
+        serialize = "M: Serialize, K: Serialize, V: Serialize, Ix: Serialize, NodeStorage<K, V, HK, Strategy, Ix>: Serialize",
+        deserialize = "M: Deserialize<'de>, K: Deserialize<'de>, V: Deserialize<'de>, Ix: Deserialize<'de>, NodeStorage<K, V, HK, Strategy, Ix>: Deserialize<'de>"
     ))
 )]
-struct HggCore<M, K, V, HK, Strategy> {
+struct HggCore<M, K, V, HK, Strategy, Ix> {
     /// The metric for this HGG.
     metric: M,
     /// The nodes of the graph. These nodes internally contain their own edges which form
     /// subgraphs of decreasing size called "layers". The lowest layer contains every node,
     /// while the highest layer contains only one node.
-    nodes: NodeStorage<K, V, HK, Strategy>,
+    nodes: NodeStorage<K, V, HK, Strategy, Ix>,
     /// This node exists on the top layer, and is the root of all searches.
-    root: usize,
+    root: Ix,
     /// The node which has been cleaned up/inserted most recently.
-    freshest: usize,
+    freshest: Ix,
     /// The number of edges in the graph on each layer.
     edges: Vec<usize>,
     /// The number of nodes in the graph on each layer.
     node_counts: Vec<usize>,
+    /// The number of nodes marked as deleted.
+    deleted: usize,
     /// The number of nearest neighbors to add on top of the number requested to increase recall.
     search_extra_knn: usize,
     /// Number of freshens per insert.
     freshens: usize,
     /// Whether to exclude all keys for which the distance has been calculated in kNN search.
     exclude_all_searched: bool,
+    /// How kNN search keeps track of the nodes it has already visited.
+    #[cfg_attr(feature = "serde", serde(default))]
+    visited: VisitedSet,
     /// Determines the number of nearest neighbors used for inserting.
     insert_knn: usize,
+    /// Whether freshening reconnects nodes which greedy search can't reach.
+    #[cfg_attr(feature = "serde", serde(default))]
+    repair_unreachable: bool,
     _phantom: PhantomData<Strategy>,
 }
 
-impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
+// SAFETY: The only thing preventing these from being derived are the raw pointers inside of `HeaderVec`
+// and the weak pointers inside of `HVec`. Every one of these points into a `HeaderVec` allocation owned by
+// a node of this same `HggCore`, and no pointer ever escapes it, so sending the `HggCore` to another thread
+// sends everything the pointers refer to along with it, just like a `Vec<Box<T>>`.
+unsafe impl<M, K, V, HK, Strategy, Ix> Send for HggCore<M, K, V, HK, Strategy, Ix>
+where
+    Ix: Send,
+    M: Send,
+    K: Send,
+    V: Send,
+    HK: Send,
+{
+}
+
+// SAFETY: The pointers inside of `HeaderVec` and `HVec` are only ever written through while holding `&mut self`.
+// All methods that take `&self` (such as searches) only read through them, and there is no interior mutability,
+// so sharing `&HggCore` between threads is sound as long as the data behind the pointers can be shared.
+unsafe impl<M, K, V, HK, Strategy, Ix> Sync for HggCore<M, K, V, HK, Strategy, Ix>
+where
+    Ix: Sync,
+    M: Sync,
+    K: Sync,
+    V: Sync,
+    HK: Sync,
+{
+}
+
+impl<M, K, V, HK, Strategy, Ix> Clone for HggCore<M, K, V, HK, Strategy, Ix>
+where
+    M: Clone,
+    K: Clone,
+    V: Clone,
+    HK: Clone,
+    Ix: NodeIndex,
+{
+    fn clone(&self) -> Self {
+        Self {
+            metric: self.metric.clone(),
+            nodes: self.nodes.clone(),
+            root: self.root,
+            freshest: self.freshest,
+            edges: self.edges.clone(),
+            node_counts: self.node_counts.clone(),
+            deleted: self.deleted,
+            search_extra_knn: self.search_extra_knn,
+            freshens: self.freshens,
+            exclude_all_searched: self.exclude_all_searched,
+            visited: self.visited,
+            insert_knn: self.insert_knn,
+            repair_unreachable: self.repair_unreachable,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<M, K, V, HK, Strategy, Ix: NodeIndex> HggCore<M, K, V, HK, Strategy, Ix> {
     /// Creates a new [`Hgg`]. It will be empty and begin with default settings.
     pub fn new(metric: M) -> Self {
         Self {
             metric,
             nodes: NodeStorage(vec![], PhantomData),
-            root: 0,
-            freshest: 0,
+            root: Ix::new(0),
+            freshest: Ix::new(0),
             edges: vec![],
             node_counts: vec![],
-            search_extra_knn: 16,
+            deleted: 0,
+            search_extra_knn: SearchParams::default().search_extra_knn,
             freshens: 1,
-            exclude_all_searched: false,
+            exclude_all_searched: SearchParams::default().exclude_all_searched,
+            visited: VisitedSet::Hash,
             insert_knn: 64,
+            repair_unreachable: false,
             _phantom: PhantomData,
         }
     }
@@ -568,6 +2133,13 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
         }
     }
 
+    /// Default value: [`VisitedSet::Hash`]
+    ///
+    /// Chooses how kNN search keeps track of the nodes it has already visited.
+    pub fn visited(self, visited: VisitedSet) -> Self {
+        Self { visited, ..self }
+    }
+
     /// Default value: `64`
     ///
     /// This controls the number of nearest neighbors used during insertion. Setting this higher will cause the graph
@@ -586,6 +2158,65 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
         Self { insert_knn, ..self }
     }
 
+    /// Default value: `false`
+    ///
+    /// If this is true, every node that is freshened is also checked to see if greedy search from the root
+    /// finds it when searching for its own key. If it doesn't, the node is disconnected and inserted again.
+    pub fn repair_unreachable(self, repair_unreachable: bool) -> Self {
+        Self {
+            repair_unreachable,
+            ..self
+        }
+    }
+
+    /// See [`HggCore::search_extra_knn`].
+    pub fn set_search_extra_knn(&mut self, search_extra_knn: usize) {
+        self.search_extra_knn = search_extra_knn;
+    }
+
+    /// See [`HggCore::freshens`].
+    pub fn set_freshens(&mut self, freshens: usize) {
+        self.freshens = freshens;
+    }
+
+    /// See [`HggCore::exclude_all_searched`].
+    pub fn set_exclude_all_searched(&mut self, exclude_all_searched: bool) {
+        self.exclude_all_searched = exclude_all_searched;
+    }
+
+    /// See [`HggCore::visited`].
+    pub fn set_visited(&mut self, visited: VisitedSet) {
+        self.visited = visited;
+    }
+
+    /// See [`HggCore::insert_knn`].
+    pub fn set_insert_knn(&mut self, insert_knn: usize) {
+        assert!(
+            insert_knn > 0,
+            "insert_knn cant be less than 1 or graph will become disconnected"
+        );
+        self.insert_knn = insert_knn;
+    }
+
+    /// See [`HggCore::repair_unreachable`].
+    pub fn set_repair_unreachable(&mut self, repair_unreachable: bool) {
+        self.repair_unreachable = repair_unreachable;
+    }
+
+    /// Returns the parameters used for kNN search by default.
+    pub fn search_params(&self) -> SearchParams {
+        SearchParams {
+            search_extra_knn: self.search_extra_knn,
+            exclude_all_searched: self.exclude_all_searched,
+        }
+    }
+
+    /// Sets the parameters used for kNN search by default.
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.search_extra_knn = params.search_extra_knn;
+        self.exclude_all_searched = params.exclude_all_searched;
+    }
+
     /// Get the (key, value) pair of a node.
     pub fn get(&self, node: usize) -> Option<(&K, &V)> {
         self.nodes.get(node).map(|node| (&node.key, &node.value))
@@ -601,6 +2232,36 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
         self.nodes.get(node).map(|node| &node.value)
     }
 
+    /// Get a mutable reference to the value of a node.
+    pub fn get_value_mut(&mut self, node: usize) -> Option<&mut V> {
+        self.nodes.get_mut(node).map(|node| &mut node.value)
+    }
+
+    /// Takes the (key, value) pairs out of the graph in order of their node index.
+    fn into_entries(self) -> Vec<(K, V)> {
+        self.nodes
+            .0
+            .into_iter()
+            .map(|HggNode { key, value, .. }| (key, value))
+            .collect()
+    }
+
+    /// Iterates over the (node, key, value) entries in order of their node index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &K, &V)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (index, &node.key, &node.value))
+    }
+
+    /// Iterates over the (node, key, value) entries in order of their node index with mutable values.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &K, &mut V)> + '_ {
+        self.nodes
+            .iter_mut()
+            .enumerate()
+            .map(|(index, node)| (index, &node.key, &mut node.value))
+    }
+
     /// Checks if the graph is empty.
     pub fn is_empty(&self) -> bool {
         self.nodes.is_empty()
@@ -611,6 +2272,30 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
         self.nodes.len()
     }
 
+    /// Checks if a node is marked as deleted.
+    pub fn is_deleted(&self, node: usize) -> bool {
+        self.nodes.get(node).is_some_and(|node| node.deleted)
+    }
+
+    /// Returns the number of nodes marked as deleted.
+    pub fn deleted_len(&self) -> usize {
+        self.deleted
+    }
+
+    /// Marks a node as deleted, but keeps it in the graph to route searches.
+    ///
+    /// Returns `false` if the node does not exist or was already marked as deleted.
+    pub fn mark_deleted(&mut self, node: usize) -> bool {
+        match self.nodes.get_mut(node) {
+            Some(node) if !node.deleted => {
+                node.deleted = true;
+                self.deleted += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Returns the number of edges in the graph on each layer.
     pub fn edges(&self) -> Vec<usize> {
         self.edges.clone()
@@ -621,7 +2306,7 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
         if self.is_empty() {
             0
         } else {
-            self.nodes[self.root].layers()
+            self.nodes[self.root.index()].layers()
         }
     }
 
@@ -670,7 +2355,7 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
                     layer_node
                         .as_slice()
                         .iter()
-                        .map(|HggEdge { neighbor, .. }| neighbor.node)
+                        .map(|HggEdge { neighbor, .. }| neighbor.node.index())
                         .collect::<Vec<_>>(),
                 );
             }
@@ -678,39 +2363,233 @@ impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy> {
         layers
     }
 
-    fn layer_node_weak(&self, layer: usize, node: usize) -> HVec<HK> {
+    /// Returns the sizes of the connected components of each layer, from largest to smallest.
+    pub fn layer_components(&self) -> Vec<Vec<usize>> {
+        let mut components = vec![];
+        for layer in 0..self.layers() {
+            let mut sizes = vec![];
+            let mut seen = vec![false; self.len()];
+            let mut stack = vec![];
+            for start in 0..self.len() {
+                if seen[start] || self.nodes[start].layers() <= layer {
+                    continue;
+                }
+                // Flood the component containing this node.
+                seen[start] = true;
+                stack.push(start);
+                let mut size = 0;
+                while let Some(node) = stack.pop() {
+                    size += 1;
+                    for HggEdge { neighbor, .. } in self.nodes[node].layers[layer].as_slice() {
+                        if !seen[neighbor.node.index()] {
+                            seen[neighbor.node.index()] = true;
+                            stack.push(neighbor.node.index());
+                        }
+                    }
+                }
+                sizes.push(size);
+            }
+            sizes.sort_unstable_by(|a, b| b.cmp(a));
+            components.push(sizes);
+        }
+        components
+    }
+
+    /// Returns every node below the top layer without a neighbor on its highest layer that is also on the layer above.
+    pub fn missing_upper_neighbors(&self) -> Vec<usize> {
+        let top = self.layers().saturating_sub(1);
+        (0..self.len())
+            .filter(|&node| {
+                let layer = self.nodes[node].layers() - 1;
+                layer != top
+                    && !self.nodes[node].layers[layer].as_slice().iter().any(
+                        |HggEdge { neighbor, .. }| {
+                            self.nodes[neighbor.node.index()].layers() > layer + 1
+                        },
+                    )
+            })
+            .collect()
+    }
+
+    /// Checks the invariants of the graph, returning the first one found to be broken.
+    ///
+    /// `cached_key_matches` checks if a key cached in a header or edge matches the key of its node.
+    ///
+    /// This never follows the weak pointers in the graph, so it is safe to call on a corrupt graph.
+    fn validate(
+        &self,
+        cached_key_matches: impl Fn(&HK, &K) -> bool,
+    ) -> Result<(), HggInvariantError> {
+        if self.is_empty() {
+            return if !self.edges.is_empty() || !self.node_counts.is_empty() {
+                Err(HggInvariantError::LayerCountMismatch {
+                    recorded: core::cmp::max(self.edges.len(), self.node_counts.len()),
+                    actual: 0,
+                })
+            } else if self.deleted != 0 {
+                Err(HggInvariantError::DeletedCountMismatch {
+                    recorded: self.deleted,
+                    actual: 0,
+                })
+            } else {
+                Ok(())
+            };
+        }
+        if self.root.index() >= self.len() {
+            return Err(HggInvariantError::RootOutOfBounds {
+                root: self.root.index(),
+            });
+        }
+        let layers = self.layers();
+        for recorded in [self.edges.len(), self.node_counts.len()] {
+            if recorded != layers {
+                return Err(HggInvariantError::LayerCountMismatch {
+                    recorded,
+                    actual: layers,
+                });
+            }
+        }
+
+        // Find the node and layer of every edge list so that edges can be checked without following them.
+        let mut layer_nodes = HashMap::with_hasher(RandomState::with_seeds(0, 0, 0, 0));
+        let mut node_counts = vec![0; layers];
+        let mut deleted = 0;
+        for (node, hgg_node) in self.nodes.iter().enumerate() {
+            if node != self.root.index() && hgg_node.layers() >= layers {
+                return Err(HggInvariantError::RootNotAlone { node });
+            }
+            for (layer, layer_node) in hgg_node.layers.iter().enumerate() {
+                if layer_node.node.index() != node {
+                    return Err(HggInvariantError::HeaderNodeMismatch {
+                        node,
+                        layer,
+                        header_node: layer_node.node.index(),
+                    });
+                }
+                if !cached_key_matches(&layer_node.key, &hgg_node.key) {
+                    return Err(HggInvariantError::HeaderKeyMismatch { node, layer });
+                }
+                node_counts[layer] += 1;
+                layer_nodes.insert(layer_node.ptr(), (node, layer));
+            }
+            deleted += hgg_node.deleted as usize;
+        }
+
+        let mut edges = vec![0; layers];
+        for (node, hgg_node) in self.nodes.iter().enumerate() {
+            for (layer, layer_node) in hgg_node.layers.iter().enumerate() {
+                let mut neighbors = HashSet::with_capacity_and_hasher(
+                    layer_node.len(),
+                    RandomState::with_seeds(0, 0, 0, 0),
+                );
+                for HggEdge { key, neighbor } in layer_node.as_slice() {
+                    let neighbor = match layer_nodes.get(&neighbor.ptr()) {
+                        Some(&(neighbor, neighbor_layer)) if neighbor_layer == layer => neighbor,
+                        _ => return Err(HggInvariantError::DanglingEdge { node, layer }),
+                    };
+                    if !neighbors.insert(neighbor) {
+                        return Err(HggInvariantError::DuplicateEdge {
+                            node,
+                            neighbor,
+                            layer,
+                        });
+                    }
+                    if !self.nodes[neighbor].layers[layer]
+                        .as_slice()
+                        .iter()
+                        .any(|edge| edge.neighbor.is(layer_node.ptr()))
+                    {
+                        return Err(HggInvariantError::AsymmetricEdge {
+                            node,
+                            neighbor,
+                            layer,
+                        });
+                    }
+                    if !cached_key_matches(key, &self.nodes[neighbor].key) {
+                        return Err(HggInvariantError::EdgeKeyMismatch {
+                            node,
+                            neighbor,
+                            layer,
+                        });
+                    }
+                }
+                edges[layer] += layer_node.len();
+            }
+        }
+
+        // Every edge was counted from both sides.
+        for (layer, (&recorded, actual)) in self.edges.iter().zip(edges).enumerate() {
+            if recorded * 2 != actual {
+                return Err(HggInvariantError::EdgeCountMismatch {
+                    layer,
+                    recorded,
+                    actual: actual / 2,
+                });
+            }
+        }
+        for (layer, (&recorded, actual)) in self.node_counts.iter().zip(node_counts).enumerate() {
+            if recorded != actual {
+                return Err(HggInvariantError::NodeCountMismatch {
+                    layer,
+                    recorded,
+                    actual,
+                });
+            }
+        }
+        if self.deleted != deleted {
+            return Err(HggInvariantError::DeletedCountMismatch {
+                recorded: self.deleted,
+                actual: deleted,
+            });
+        }
+
+        // Follow the freshening order, which must visit every node once and come back to the freshest node.
+        let mut visited = vec![false; self.len()];
+        let mut node = self.freshest.index();
+        for _ in 0..self.len() {
+            if node >= self.len() || visited[node] {
+                return Err(HggInvariantError::BrokenFresheningOrder { node });
+            }
+            visited[node] = true;
+            node = self.nodes[node].next.index();
+        }
+        if node != self.freshest.index() {
+            return Err(HggInvariantError::BrokenFresheningOrder { node });
+        }
+
+        Ok(())
+    }
+
+    fn layer_node_weak(&self, layer: usize, node: usize) -> HVec<HK, Ix> {
         unsafe { HVec(self.nodes[node].layers[layer].weak()) }
     }
 }
-impl<M, K, V, HK, Strategy> HggCore<M, K, V, HK, Strategy>
+impl<M, K, V, HK, Strategy, Ix> HggCore<M, K, V, HK, Strategy, Ix>
 where
     M: Metric<K>,
-    Self: HggInternal<M = M, K = K, V = V, HK = HK>,
+    Self: HggInternal<M = M, K = K, V = V, HK = HK, Ix = Ix>,
+    Ix: NodeIndex,
 {
     /// Insert a (key, value) pair.
     fn insert(&mut self, key: K, value: V) -> usize {
-        // Add the node (it will be added this way regardless).
-        let node = self.nodes.len();
-        // Create the node.
-        // The current freshest node's `next` is the stalest node, which will subsequently become
-        // the freshest when freshened. If this is the only node, looking up the freshest node will fail.
-        // Due to that, we set this node's next to itself if its the only node.
-        let next = if node == 0 {
-            0
-        } else {
-            self.nodes[self.freshest].next
-        };
-        self.nodes.push(HggNode {
+        self.insert_visit(
+            &mut Scratch::with_visited(self.visited),
             key,
             value,
-            layers: vec![],
-            next,
-        });
-        // The previous freshest node should now be freshened right before this node, as this node is now fresher.
-        // Even if this is the only node, this will still work because this node still comes after itself in the freshening order.
-        self.nodes[self.freshest].next = node;
-        // This is now the freshest node.
-        self.freshest = node;
+            &mut (),
+        )
+    }
+
+    /// Insert a (key, value) pair using the memory in `scratch`, visiting the searches for its neighbors.
+    fn insert_visit(
+        &mut self,
+        scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>,
+        key: K,
+        value: V,
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) -> usize {
+        // Add the node (it will be added this way regardless).
+        let node = self.push_node(key, value);
 
         if node == 0 {
             // Push the new layer 0.
@@ -718,12 +2597,28 @@ where
             self.edges.push(0);
             self.node_counts.push(1);
             // Set the root.
-            self.root = 0;
+            self.root = Ix::new(0);
             return 0;
         }
+        self.link(scratch, node, visitor);
+
+        // Freshen the graph to clean up older nodes.
+        self.freshen(scratch);
+
+        node
+    }
 
+    /// Connects a node without any layers to the graph, adding it to as many layers as required.
+    ///
+    /// There must be at least one other node in the graph.
+    fn link(
+        &mut self,
+        scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>,
+        node: usize,
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) {
         // Find nearest neighbor path via greedy search.
-        let path = self.search_path(&self.nodes[node].key);
+        let path = self.search_path(&self.nodes[node].key, visitor);
 
         for (layer, (found, distance)) in path.into_iter().enumerate() {
             // Add the new layer to this node.
@@ -736,7 +2631,7 @@ where
                 // Add edge to nearest neighbor (the only other node in this layer, the old root).
                 self.layer_add_edge(layer, found, node);
                 // Set the root to this node.
-                self.root = node;
+                self.root = Ix::new(node);
                 // Create the new layer (totally empty).
                 self.add_node_layer(node);
                 self.edges.push(0);
@@ -745,7 +2640,8 @@ where
                 break;
             }
 
-            self.optimize_layer_neighborhood(layer, node, found, distance, false);
+            visitor.enter_layer(layer);
+            self.optimize_layer_neighborhood(scratch, layer, node, found, distance, false, visitor);
 
             // Check if any surrounding nodes are on the next layer.
             if self.any_neighbors_above_layer(layer, node) {
@@ -753,68 +2649,476 @@ where
                 break;
             }
         }
+    }
 
-        // Freshen the graph to clean up older nodes.
-        self.freshen();
+    /// Insert a batch of (key, value) pairs.
+    fn insert_batch(&mut self, entries: impl IntoIterator<Item = (K, V)>) -> Range<usize> {
+        let mut scratch = Scratch::with_visited(self.visited);
+        self.insert_batch_with_search(entries, |hgg, chunk| {
+            chunk
+                .iter()
+                .map(|(key, _)| hgg.search_insert_knn(&mut scratch, key))
+                .collect()
+        })
+    }
+
+    /// Adds a node that is not yet connected on any layer and makes it the freshest node.
+    fn push_node(&mut self, key: K, value: V) -> usize {
+        let node = self.nodes.len();
+        // Check this before the graph is changed so that it is left intact if this panics.
+        assert!(
+            node <= <Ix as NodeIndex>::max(),
+            "the graph can't hold more than {} nodes with its node index type",
+            <Ix as NodeIndex>::max().saturating_add(1)
+        );
+        // Create the node.
+        // The current freshest node's `next` is the stalest node, which will subsequently become
+        // the freshest when freshened. If this is the only node, looking up the freshest node will fail.
+        // Due to that, we set this node's next to itself if its the only node.
+        let next = if node == 0 {
+            0
+        } else {
+            self.nodes[self.freshest.index()].next.index()
+        };
+        self.nodes.push(HggNode {
+            key,
+            value,
+            layers: vec![],
+            next: Ix::new(next),
+            deleted: false,
+        });
+        // The previous freshest node should now be freshened right before this node, as this node is now fresher.
+        // Even if this is the only node, this will still work because this node still comes after itself in the freshening order.
+        self.nodes[self.freshest.index()].next = Ix::new(node);
+        // This is now the freshest node.
+        self.freshest = Ix::new(node);
 
         node
     }
 
+    /// Insert a batch of (key, value) pairs.
+    ///
+    /// `search_chunk` must return the result of [`HggCore::search_insert_knn`] for every key in the chunk.
+    /// This allows the searches to be performed in parallel, but the nodes are always linked into the graph
+    /// and freshened serially. The chunk is compared against itself by brute force, taking `BATCH_CHUNK_LEN² / 2`
+    /// distance computations.
+    fn insert_batch_with_search(
+        &mut self,
+        entries: impl IntoIterator<Item = (K, V)>,
+        mut search_chunk: impl FnMut(&Self, &[(K, V)]) -> Vec<Vec<(usize, M::Unit)>>,
+    ) -> Range<usize> {
+        let start = self.len();
+        let mut entries = entries.into_iter().peekable();
+
+        // The graph needs at least two nodes so that the bottom layer is not the top layer.
+        while self.len() < 2 {
+            if let Some((key, value)) = entries.next() {
+                self.insert(key, value);
+            } else {
+                return start..self.len();
+            }
+        }
+
+        let mut scratch = Scratch::with_visited(self.visited);
+        while entries.peek().is_some() {
+            let chunk: Vec<(K, V)> = (&mut entries).take(BATCH_CHUNK_LEN).collect();
+            let searches = search_chunk(self, &chunk);
+            let chunk_start = self.len();
+            for ((key, value), mut knn) in chunk.into_iter().zip(searches) {
+                let node = self.push_node(key, value);
+                // The search could not find the nodes inserted earlier in this chunk, so add them now.
+                for other in chunk_start..node {
+                    let distance = self
+                        .metric
+                        .distance(&self.nodes[other].key, &self.nodes[node].key);
+                    let pos = knn.partition_point(|&(_, d)| d <= distance);
+                    knn.insert(pos, (other, distance));
+                }
+                knn.truncate(self.insert_knn);
+                let knn: Vec<usize> = knn.into_iter().map(|(neighbor, _)| neighbor).collect();
+
+                // Connect the node on the bottom layer, and then add it to higher layers as required.
+                self.add_node_layer(node);
+                self.node_counts[0] += 1;
+                self.connect_layer_neighborhood(0, node, &knn);
+                self.raise(&mut scratch, node);
+            }
+            // Freshen the graph as much as it would have been freshened by inserting individually.
+            for _ in chunk_start..self.len() {
+                self.freshen(&mut scratch);
+            }
+        }
+
+        start..self.len()
+    }
+
     /// Optimizes a number of stale nodes equal to `self.freshens`.
     ///
     /// You do not need to call this yourself, as it is called on insert.
-    fn freshen(&mut self) {
+    fn freshen(&mut self, scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>) {
         let freshens = self.freshens;
         for node in self.stales().take(freshens).collect::<Vec<_>>() {
             // Start by reducing as many connections as possible on the layers it exists.
             for layer in 0..self.nodes[node].layers() {
-                self.optimize_layer_neighborhood(layer, node, node, M::Unit::zero(), true)
+                self.optimize_layer_neighborhood(
+                    scratch,
+                    layer,
+                    node,
+                    node,
+                    M::Unit::zero(),
+                    true,
+                    &mut (),
+                )
             }
             // Next we want to check, starting on this node's highest layer, if it should be added to the next layer.
-            for layer in self.nodes[node].layers() - 1..self.layers() {
-                // An edge case occurs if we are on the top layer.
-                if layer == self.layers() - 1 {
-                    // Check if this node is the root node.
-                    if node != self.root {
-                        // In this case, we just raised this node to this layer, and now we need to add a new layer.
-                        // Set the root to this node.
-                        self.root = node;
-                        // Create the new layer (totally empty).
-                        self.add_node_layer(node);
-                        self.edges.push(0);
-                        self.node_counts.push(1);
-                    }
-                    // In either case, we are now done, as the top layer now has one node,
-                    // regardless of if it is this node or the other node.
-                    break;
+            self.raise(scratch, node);
+            // Reconnect the node if greedy search can't find it. A graph with one node has nothing to reconnect.
+            if self.repair_unreachable && self.len() > 1 && !self.is_reachable(node) {
+                self.relink(scratch, node);
+            }
+        }
+    }
+
+    /// Adds a node to higher layers until one of its neighbors exists on the layer above it.
+    ///
+    /// If the node reaches the top layer, it becomes the root and a new top layer is created.
+    fn raise(&mut self, scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>, node: usize) {
+        for layer in self.nodes[node].layers() - 1..self.layers() {
+            // An edge case occurs if we are on the top layer.
+            if layer == self.layers() - 1 {
+                // Check if this node is the root node.
+                if node != self.root.index() {
+                    // In this case, we just raised this node to this layer, and now we need to add a new layer.
+                    // Set the root to this node.
+                    self.root = Ix::new(node);
+                    // Create the new layer (totally empty).
+                    self.add_node_layer(node);
+                    self.edges.push(0);
+                    self.node_counts.push(1);
                 }
+                // In either case, we are now done, as the top layer now has one node,
+                // regardless of if it is this node or the other node.
+                break;
+            }
 
-                // Check if there are no neighbors on the next layer.
-                // Check if any surrounding nodes are on the next layer.
-                if self
-                    .layer_node_weak(layer, node)
-                    .as_slice()
-                    .iter()
-                    .any(|HggEdge { neighbor, .. }| self.nodes[neighbor.node].layers() > layer + 1)
-                {
-                    // If any of the neighbors are on the next layer up, we don't need to add this node to more layers.
-                    break;
+            // Check if there are no neighbors on the next layer.
+            // Check if any surrounding nodes are on the next layer.
+            if self.any_neighbors_above_layer(layer, node) {
+                // If any of the neighbors are on the next layer up, we don't need to add this node to more layers.
+                break;
+            }
+
+            // Add the new layer to this node.
+            self.add_node_layer(node);
+            // Note that since we are adding it to the NEXT layer, this (and further uses of layer)
+            // are layer + 1.
+            self.node_counts[layer + 1] += 1;
+
+            // Find the nearest neighbor on the next layer (by greedy search).
+            let (nn, distance) = self
+                .search_to_layer(layer + 1, &self.nodes[node].key, &mut ())
+                .unwrap();
+
+            self.optimize_layer_neighborhood(
+                scratch,
+                layer + 1,
+                node,
+                nn,
+                distance,
+                false,
+                &mut (),
+            );
+        }
+    }
+
+    /// Checks if greedy search from the root finds a node when searching for its own key.
+    ///
+    /// Finding another node with a distance of zero (a duplicate key) also counts.
+    fn is_reachable(&self, node: usize) -> bool {
+        self.search(&self.nodes[node].key)
+            .is_some_and(|(found, distance)| found == node || distance == M::Unit::zero())
+    }
+
+    /// Returns every node which greedy search from the root can't find when searching for its own key.
+    fn unreachable(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&node| !self.is_reachable(node))
+            .collect()
+    }
+
+    /// Disconnects a node from the graph and connects it again, as if it were inserted again.
+    fn relink(&mut self, scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>, node: usize) {
+        let mut affected = self.unlink(node);
+        self.link(scratch, node, &mut ());
+
+        // The former neighbors may have lost their only neighbor on the layer above them.
+        affected.sort_unstable();
+        affected.dedup();
+        for neighbor in affected {
+            self.raise(scratch, neighbor);
+        }
+    }
+
+    /// Changes the key of a node without changing its index, returning the old key.
+    ///
+    /// The node is disconnected from the graph and then connected again at its new location.
+    fn update_key(&mut self, node: usize, key: K) -> Option<K> {
+        if node >= self.len() {
+            return None;
+        }
+        if self.len() == 1 {
+            // The only node has no edges, so only the key cached in its header needs to change.
+            let old_key = mem::replace(&mut self.nodes[node].key, key);
+            self.nodes[node].layers.clear();
+            self.add_node_layer(node);
+            return Some(old_key);
+        }
+
+        // Once the node is disconnected, no edges refer to it, so no neighbor caches the old key.
+        let mut affected = self.unlink(node);
+        let old_key = mem::replace(&mut self.nodes[node].key, key);
+        // The headers and edges of the node are recreated with the new key.
+        let mut scratch = Scratch::with_visited(self.visited);
+        self.link(&mut scratch, node, &mut ());
+
+        // The former neighbors may have lost their only neighbor on the layer above them.
+        affected.sort_unstable();
+        affected.dedup();
+        for neighbor in affected {
+            self.raise(&mut scratch, neighbor);
+        }
+
+        Some(old_key)
+    }
+
+    /// Removes a node from the graph, returning its (key, value) pair.
+    ///
+    /// The last node is moved into the index of the removed node.
+    fn remove(&mut self, node: usize) -> Option<(K, V)> {
+        if node >= self.len() {
+            return None;
+        }
+        if self.nodes[node].deleted {
+            self.deleted -= 1;
+        }
+        if self.len() == 1 {
+            // Removing the only node leaves the graph totally empty.
+            self.clear_graph();
+            let HggNode { key, value, .. } = self.nodes.pop().unwrap();
+            return Some((key, value));
+        }
+
+        // Take the node out of the freshening order. It is singly linked, so this scans every node.
+        let previous = self
+            .nodes
+            .iter()
+            .position(|other| other.next.index() == node)
+            .expect("fatal; every node must be in the freshening order");
+        self.nodes[previous].next = self.nodes[node].next;
+        if self.freshest.index() == node {
+            self.freshest = Ix::new(previous);
+        }
+
+        // Disconnect the node from the graph.
+        let mut affected = self.unlink(node);
+
+        // Move the last node into the index of the removed node.
+        let last = self.len() - 1;
+        let HggNode { key, value, .. } = self.nodes.swap_remove(node);
+        if node != last {
+            self.renumber(last, node);
+            for neighbor in &mut affected {
+                if *neighbor == last {
+                    *neighbor = node;
                 }
+            }
+        }
 
-                // Add the new layer to this node.
-                self.add_node_layer(node);
-                // Note that since we are adding it to the NEXT layer, this (and further uses of layer)
-                // are layer + 1.
-                self.node_counts[layer + 1] += 1;
+        // The former neighbors may have lost their only neighbor on the layer above them.
+        affected.sort_unstable();
+        affected.dedup();
+        let mut scratch = Scratch::with_visited(self.visited);
+        for neighbor in affected {
+            self.raise(&mut scratch, neighbor);
+        }
 
-                // Find the nearest neighbor on the next layer (by greedy search).
-                let (nn, distance) = self
-                    .search_to_layer(layer + 1, &self.nodes[node].key)
-                    .unwrap();
+        Some((key, value))
+    }
+
+    /// Physically removes every node marked as deleted from the graph.
+    ///
+    /// Only the neighborhoods of the deleted nodes are repaired. The remaining nodes keep their relative order.
+    ///
+    /// Returns a map from the old index of every node to its new index, or `None` if it was removed.
+    fn compact(&mut self) -> Vec<Option<usize>> {
+        if self.deleted == self.len() {
+            // Nothing survives, so the graph becomes totally empty.
+            let map = vec![None; self.len()];
+            self.clear_graph();
+            self.nodes.clear();
+            return map;
+        }
+
+        // Disconnect every deleted node from the graph.
+        // There is at least one node that isn't deleted, so there is always another node to become the root.
+        let mut affected = vec![];
+        for node in 0..self.len() {
+            if self.nodes[node].deleted {
+                affected.append(&mut self.unlink(node));
+            }
+        }
+
+        // Assign the new indices in order.
+        let mut map = vec![None; self.len()];
+        let mut new_index = 0;
+        for (old_index, node) in self.nodes.iter().enumerate() {
+            if !node.deleted {
+                map[old_index] = Some(new_index);
+                new_index += 1;
+            }
+        }
+
+        // Get the freshening order (from stalest to freshest) of the nodes that remain.
+        let mut node = self.freshest.index();
+        let order: Vec<usize> = iter::repeat_with(|| {
+            node = self.nodes[node].next.index();
+            map[node]
+        })
+        .take(self.len())
+        .flatten()
+        .collect();
+
+        // Remove the deleted nodes and update the indices of the remaining nodes.
+        self.nodes.retain(|node| !node.deleted);
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            for layer in &mut node.layers {
+                layer.node = Ix::new(index);
+            }
+        }
+        for (&node, &next) in order.iter().zip(order.iter().cycle().skip(1)) {
+            self.nodes[node].next = Ix::new(next);
+        }
+        self.freshest = Ix::new(*order.last().unwrap());
+        self.root = Ix::new(
+            map[self.root.index()].expect("fatal; the root must not be deleted after compacting"),
+        );
+        self.deleted = 0;
+
+        // The former neighbors may have lost their only neighbor on the layer above them.
+        let mut affected: Vec<usize> = affected.into_iter().filter_map(|node| map[node]).collect();
+        affected.sort_unstable();
+        affected.dedup();
+        let mut scratch = Scratch::with_visited(self.visited);
+        for neighbor in affected {
+            self.raise(&mut scratch, neighbor);
+        }
+
+        map
+    }
+
+    /// Disconnects a node from every layer it exists on and repairs the graph around it.
+    ///
+    /// The node is left in `self.nodes` without any layers. If the node was the root,
+    /// one of its neighbors becomes the new root. There must be at least one other node in the graph.
+    ///
+    /// Returns the former neighbors of the node on all layers.
+    fn unlink(&mut self, node: usize) -> Vec<usize> {
+        let mut affected = vec![];
+        let mut layers_neighbors = vec![];
+        for layer in 0..self.nodes[node].layers() {
+            let old_neighbors = self.disconnect_layer_neighbors(layer, node);
+            self.node_counts[layer] -= 1;
+            // Paths between the old neighbors may have gone through this node, so make sure they still exist.
+            self.reconnect_layer_nodes(layer, &old_neighbors);
+            affected.extend_from_slice(&old_neighbors);
+            layers_neighbors.push(old_neighbors);
+        }
+        self.nodes[node].layers.clear();
+
+        if node == self.root.index() {
+            // The root was the only node on the top layer, so that layer is removed with it.
+            self.edges.pop();
+            self.node_counts.pop();
+            let top = self.edges.len() - 1;
+            // The closest neighbor of the old root on the new top layer becomes the new root.
+            // This relies on `disconnect_layer_neighbors` returning the neighbors sorted by distance.
+            debug_assert!(layers_neighbors[top].windows(2).all(|pair| {
+                let key = &self.nodes[node].key;
+                self.metric.distance(key, &self.nodes[pair[0]].key)
+                    <= self.metric.distance(key, &self.nodes[pair[1]].key)
+            }));
+            let root = layers_neighbors[top]
+                .first()
+                .copied()
+                .or_else(|| (0..self.len()).find(|&other| self.nodes[other].layers() > top))
+                .expect("fatal; there must be another node on the layer below the root");
+            self.root = Ix::new(root);
+            // Create the new layer (totally empty).
+            self.add_node_layer(root);
+            self.edges.push(0);
+            self.node_counts.push(1);
+        }
+
+        self.trim_layers();
+        affected
+    }
+
+    /// Resets the graph structure to be empty. Does not remove the nodes themselves.
+    fn clear_graph(&mut self) {
+        self.root = Ix::new(0);
+        self.freshest = Ix::new(0);
+        self.edges.clear();
+        self.node_counts.clear();
+        self.deleted = 0;
+    }
+
+    /// Ensures that every node in `nodes` can reach every other node in `nodes` by greedy search on `layer`.
+    fn reconnect_layer_nodes(&mut self, layer: usize, nodes: &[usize]) {
+        for &from in nodes {
+            for &to in nodes {
+                if from == to {
+                    continue;
+                }
+                let from = self.layer_node_weak(layer, from);
+                let mut to = self.layer_node_weak(layer, to);
+                let query = self.node_get_key(&to);
+                let from_distance = self.metric.distance(self.node_get_key(&from), query);
+                let (mut found, _) = self.search_layer(from, from_distance, query, &mut ());
+                if found != to {
+                    // Greedy search got stuck, so give it a direct path to the target.
+                    self.layer_add_edge_dedup_weak(layer, &mut found, &mut to);
+                }
+            }
+        }
+    }
+
+    /// Removes layers from the top of the graph until the layer below the top contains more nodes than just the root.
+    fn trim_layers(&mut self) {
+        while self.layers() > 1 && self.node_counts[self.layers() - 2] == 1 {
+            self.nodes[self.root.index()].layers.pop();
+            self.edges.pop();
+            self.node_counts.pop();
+        }
+    }
 
-                self.optimize_layer_neighborhood(layer + 1, node, nn, distance, false);
+    /// Moves all references to the node at index `from` to index `to`.
+    ///
+    /// The node itself must already be located at index `to`.
+    fn renumber(&mut self, from: usize, to: usize) {
+        for layer in &mut self.nodes[to].layers {
+            layer.node = Ix::new(to);
+        }
+        for node in self.nodes.iter_mut() {
+            if node.next.index() == from {
+                node.next = Ix::new(to);
             }
         }
+        if self.root.index() == from {
+            self.root = Ix::new(to);
+        }
+        if self.freshest.index() == from {
+            self.freshest = Ix::new(to);
+        }
     }
 
     /// Searches for the nearest neighbor greedily from the top layer to the bottom.
@@ -823,23 +3127,23 @@ where
     ///
     /// Returns `(node, distance)`.
     fn search(&self, query: &K) -> Option<(usize, M::Unit)> {
-        self.search_to_layer(0, query)
+        self.search_to_layer(0, query, &mut ())
     }
 
     /// Produces the stalest nodes and marks them as now the freshest nodes when consumed.
     ///
     /// This iterator is infinite, and will iterate through every entry in a specific order before repeating.
     fn stales(&mut self) -> impl Iterator<Item = usize> + '_ {
-        let mut node = self.freshest;
+        let mut node = self.freshest.index();
         core::iter::from_fn(move || {
-            node = self.nodes[node].next;
-            self.freshest = node;
+            node = self.nodes[node].next.index();
+            self.freshest = Ix::new(node);
             Some(node)
         })
     }
 
     /// Updates the `HeaderVecWeak` in neighbors of this node.
-    fn update_weak(&mut self, mut node: HVec<HK>, previous: *const (), add_last: bool) {
+    fn update_weak(&mut self, mut node: HVec<HK, Ix>, previous: *const (), add_last: bool) {
         let old_len = if add_last { node.len() } else { node.len() - 1 };
         let weak = node.weak();
         for HggEdge { neighbor, .. } in &mut node[..old_len] {
@@ -866,8 +3170,8 @@ where
     fn layer_add_edge_dedup_weak(
         &mut self,
         layer: usize,
-        a: &mut HVec<HK>,
-        b: &mut HVec<HK>,
+        a: &mut HVec<HK, Ix>,
+        b: &mut HVec<HK, Ix>,
     ) -> bool {
         if !a.contains(b) {
             self.layer_add_edge_weak(layer, a, b);
@@ -877,48 +3181,28 @@ where
         }
     }
 
-    /// Finds the nearest neighbor to the query key starting from the `from` node using greedy search.
+    /// Searches for the nodes on the bottom layer that a key would be connected to if it were inserted.
     ///
-    /// Returns `(node, distance)`.
-    fn search_layer_from_weak(
+    /// Returns `(node, distance)` sorted by distance.
+    fn search_insert_knn(
         &self,
-        from: HVec<HK>,
-        from_distance: M::Unit,
-        query: &K,
-    ) -> (HVec<HK>, M::Unit) {
-        let mut best_weak = from;
-        let mut best_distance = from_distance;
-
-        while let Some((neighbor_weak, distance)) = self.best_neighbor_distance(&best_weak, query) {
-            if distance < best_distance {
-                best_weak = neighbor_weak.weak();
-                best_distance = distance;
-            } else {
-                break;
-            }
-        }
-        (best_weak, best_distance)
-    }
-
-    /// Searches for the nearest neighbor greedily from the top layer to the bottom.
-    ///
-    /// This is faster than calling [`Hgg::search_knn`] with `num` of `1`.
-    ///
-    /// Returns `(node, distance)`.
-    fn search_to_layer(&self, final_layer: usize, query: &K) -> Option<(usize, M::Unit)> {
-        if self.is_empty() {
-            return None;
-        }
-        let mut node = self.root;
-        let mut distance = self.metric.distance(&self.nodes[node].key, query);
-        // This assumes that the top layer only contains one node (as it should).
-        for layer in (final_layer..self.layers() - 1).rev() {
-            let node_weak = self.layer_node_weak(layer, node);
-            let (new_node, new_distance) = self.search_layer_from_weak(node_weak, distance, query);
-            node = new_node.node;
-            distance = new_distance;
+        scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>,
+        key: &K,
+    ) -> Vec<(usize, M::Unit)> {
+        if let Some((node, distance)) = self.search_to_layer(0, key, &mut ()) {
+            self.search_layer_knn_from_weak(
+                scratch,
+                self.layer_node_weak(0, node),
+                distance,
+                key,
+                self.insert_knn,
+                &mut (),
+            )
+            .map(|(weak, distance)| (weak.node.index(), distance))
+            .collect()
+        } else {
+            vec![]
         }
-        Some((node, distance))
     }
 
     /// Searches for the nearest neighbor greedily from the top layer to the bottom.
@@ -926,115 +3210,72 @@ where
     /// This is faster than calling [`Hgg::search_knn`] with `num` of `1`.
     ///
     /// Returns the greedy search result on every layer as `(node, distance)`.
-    fn search_path(&self, query: &K) -> Vec<(usize, M::Unit)> {
+    fn search_path(
+        &self,
+        query: &K,
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) -> Vec<(usize, M::Unit)> {
         if self.is_empty() {
             return vec![];
         }
-        let init_node = self.root;
+        let init_node = self.root.index();
+        visitor.enter_layer(self.layers() - 1);
         let init_distance = self.metric.distance(&self.nodes[init_node].key, query);
+        visitor.distance(init_node, init_distance);
         let mut path: Vec<(usize, M::Unit)> = iter::repeat_with(|| (init_node, init_distance))
             .take(self.layers())
             .collect();
         // This assumes that the top layer only contains one node (as it should).
         for layer in (0..self.layers() - 1).rev() {
+            visitor.enter_layer(layer);
             let node = self.layer_node_weak(layer, path[layer + 1].0);
             let distance = path[layer + 1].1;
-            let (node, distance) = self.search_layer_from_weak(node, distance, query);
-            path[layer] = (node.node, distance);
+            let (node, distance) = self.search_layer(node, distance, query, visitor);
+            path[layer] = (node.node.index(), distance);
         }
         path
     }
 
     /// Finds the knn greedily from a starting node `from`.
     ///
-    /// Returns (node, distance, searched) pairs. `searched` will always be true, so you can ignore it.
-    fn search_layer_knn_from_weak(
+    /// Returns `(node, distance)` pairs, which are drained from the `scratch`.
+    fn search_layer_knn_from_weak<'a>(
         &self,
-        from: HVec<HK>,
+        scratch: &'a mut Scratch<HVec<HK, Ix>, M::Unit>,
+        from: HVec<HK, Ix>,
         from_distance: M::Unit,
         query: &K,
         num: usize,
-    ) -> Vec<(HVec<HK>, M::Unit, bool)> {
-        if num == 0 {
-            return vec![];
-        }
-        // Perform a greedy search first to save time.
-        let (from, from_distance) = self.search_layer_from_weak(from, from_distance, query);
-        // Contains the index and the distance as a pair.
-        let mut bests = vec![(from.weak(), from_distance, false)];
-
-        // This set is used to more quickly determine if a node is contained in the best set.
-        let mut exclude = HashSet::with_capacity_and_hasher(
-            num.saturating_mul(2),
-            RandomState::with_seeds(0, 0, 0, 0),
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) -> impl Iterator<Item = (HVec<HK, Ix>, M::Unit)> + 'a {
+        self.search_layer_knn_filtered(
+            scratch,
+            from,
+            from_distance,
+            query,
+            num,
+            &self.search_params(),
+            |_| true,
+            visitor,
         );
-        exclude.insert(from);
-
-        loop {
-            if let Some((previous_node, _, searched)) =
-                bests.iter_mut().find(|&&mut (_, _, searched)| !searched)
-            {
-                // Set this as searched (we are searching it now).
-                *searched = true;
-                // Erase the reference to the search node (to avoid lifetime & borrowing issues).
-                let previous_node = previous_node.weak();
-                for edge in previous_node.as_slice() {
-                    // TODO: Try this as a BTreeSet.
-                    // Make sure that we don't have a copy of this node already or we will get duplicates.
-                    if exclude.contains(&edge.neighbor) {
-                        continue;
-                    }
-
-                    // Compute the distance from the query.
-                    let distance = self.metric.distance(query, self.edge_get_key(edge));
-                    // If we dont have enough yet, add it.
-                    if bests.len() < num {
-                        bests.insert(
-                            bests.partition_point(|&(_, best_distance, _)| {
-                                best_distance <= distance
-                            }),
-                            (edge.neighbor.weak(), distance, false),
-                        );
-                        exclude.insert(edge.neighbor.weak());
-                    } else if distance < bests.last().unwrap().1 {
-                        // Otherwise only add it if its better than the worst item we have.
-                        // Remove the worst item we have now and exclude it if exclude_all_searched is set.
-                        if self.exclude_all_searched {
-                            let (old_node, _, _) = bests.pop().unwrap();
-                            exclude.remove(&old_node);
-                        } else {
-                            bests.pop();
-                        }
-                        exclude.insert(edge.neighbor.weak());
-                        bests.insert(
-                            bests.partition_point(|&(_, best_distance, _)| {
-                                best_distance <= distance
-                            }),
-                            (edge.neighbor.weak(), distance, false),
-                        );
-                    }
-                }
-            } else {
-                return bests;
-            }
-        }
+        scratch.drain_neighbors()
     }
 
     fn any_neighbors_above_layer(&self, layer: usize, node: usize) -> bool {
         self.layer_node_weak(layer, node)
             .as_slice()
             .iter()
-            .any(|HggEdge { neighbor, .. }| self.nodes[neighbor.node].layers() > layer + 1)
+            .any(|HggEdge { neighbor, .. }| self.nodes[neighbor.node.index()].layers() > layer + 1)
     }
 
-    fn layer_add_edge_weak(&mut self, layer: usize, a: &mut HVec<HK>, b: &mut HVec<HK>) {
+    fn layer_add_edge_weak(&mut self, layer: usize, a: &mut HVec<HK, Ix>, b: &mut HVec<HK, Ix>) {
         // Add the edge from a to b.
         let edge = self.make_edge_to_node(b);
         // Insert it onto the end.
         if let Some(previous) = a.push(edge) {
             // Update the strong reference first.
             unsafe {
-                self.nodes[a.node].layers[layer].update(a.weak().0);
+                self.nodes[a.node.index()].layers[layer].update(a.weak().0);
             }
             // Update the neighbors.
             self.update_weak(a.weak(), previous, false);
@@ -1046,7 +3287,7 @@ where
         if let Some(previous) = b.push(edge) {
             // Update the strong reference first.
             unsafe {
-                self.nodes[b.node].layers[layer].update(b.weak().0);
+                self.nodes[b.node.index()].layers[layer].update(b.weak().0);
             }
             // Update the neighbors.
             self.update_weak(b.weak(), previous, true);
@@ -1055,45 +3296,150 @@ where
         self.edges[layer] += 1;
     }
 
-    fn best_neighbor_distance(&self, node: &HVec<HK>, query: &K) -> Option<(HVec<HK>, M::Unit)> {
-        node.as_slice()
-            .iter()
-            .map(|edge| {
-                (
-                    edge.neighbor.weak(),
-                    self.metric.distance(self.edge_get_key(edge), query),
-                )
-            })
-            .min_by_key(|(_, distance)| *distance)
+    /// Searches for the nearest neighbor greedily from the top layer to the bottom.
+    ///
+    /// This implementation starts with 1nn search until the bottom layer and then
+    /// performs kNN search. Nodes marked as deleted are not returned.
+    ///
+    /// Returns `(node, distance)`.
+    fn search_knn(
+        &self,
+        scratch: &mut Scratch<HVec<HK, Ix>, M::Unit>,
+        query: &K,
+        num: usize,
+        params: &SearchParams,
+        visitor: &mut impl SearchVisitor<M::Unit>,
+    ) -> Vec<(usize, M::Unit)> {
+        if self.deleted == 0 {
+            self.search_knn_filtered(scratch, query, num, params, |_| true, visitor)
+        } else {
+            self.search_knn_filtered(
+                scratch,
+                query,
+                num,
+                params,
+                |node| !self.nodes[node].deleted,
+                visitor,
+            )
+        }
     }
 
-    /// Searches for the nearest neighbor greedily from the top layer to the bottom.
+    /// Searches for the nearest neighbors whose entries pass the `filter` greedily from the top layer to the bottom.
+    ///
+    /// The filter is given the index, key, and value of each node. Nodes marked as deleted are not returned.
+    ///
+    /// Returns `(node, distance)`.
+    fn search_knn_filtered_entries(
+        &self,
+        query: &K,
+        num: usize,
+        filter: impl Fn(usize, &K, &V) -> bool,
+    ) -> Vec<(usize, M::Unit)> {
+        self.search_knn_filtered(
+            &mut Scratch::with_visited(self.visited),
+            query,
+            num,
+            &self.search_params(),
+            |node| {
+                let HggNode {
+                    key,
+                    value,
+                    deleted,
+                    ..
+                } = &self.nodes[node];
+                !deleted && filter(node, key, value)
+            },
+            &mut (),
+        )
+    }
+
+    /// Searches for every node within `radius` of the query.
     ///
     /// This implementation starts with 1nn search until the bottom layer and then
-    /// performs kNN search.
+    /// performs a radius search. Nodes marked as deleted are not returned.
     ///
     /// Returns `(node, distance)`.
-    fn search_knn(&self, query: &K, num: usize) -> impl Iterator<Item = (usize, M::Unit)> + '_ {
-        let mapfn = |(weak, distance, _): (HVec<HK>, M::Unit, bool)| (weak.node, distance);
-        if let Some((node, distance)) = self.search_to_layer(0, query) {
-            self.search_layer_knn_from_weak(
-                self.layer_node_weak(0, node),
-                distance,
+    fn search_within(&self, query: &K, radius: M::Unit, slack: usize) -> Vec<(usize, M::Unit)> {
+        if self.deleted == 0 {
+            self.search_within_filtered(
+                &mut Scratch::with_visited(self.visited),
                 query,
-                num + self.search_extra_knn,
+                radius,
+                slack,
+                &self.search_params(),
+                |_| true,
+                &mut (),
             )
-            .into_iter()
-            .take(num)
-            .map(mapfn)
         } else {
-            vec![].into_iter().take(num).map(mapfn)
+            self.search_within_filtered(
+                &mut Scratch::with_visited(self.visited),
+                query,
+                radius,
+                slack,
+                &self.search_params(),
+                |node| !self.nodes[node].deleted,
+                &mut (),
+            )
+        }
+    }
+}
+
+impl<M, K, V, HK, Strategy, Ix> SearchGraph for HggCore<M, K, V, HK, Strategy, Ix>
+where
+    M: Metric<K>,
+    Self: HggInternal<M = M, K = K, V = V, HK = HK, Ix = Ix>,
+    Ix: NodeIndex,
+{
+    type M = M;
+    type K = K;
+    type Node = HVec<HK, Ix>;
+
+    fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    fn root(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.root.index())
         }
     }
+
+    fn layers(&self) -> usize {
+        HggCore::layers(self)
+    }
+
+    fn len(&self) -> usize {
+        HggCore::len(self)
+    }
+
+    fn key(&self, node: usize) -> &K {
+        &self.nodes[node].key
+    }
+
+    fn layer_node(&self, layer: usize, node: usize) -> HVec<HK, Ix> {
+        self.layer_node_weak(layer, node)
+    }
+
+    fn node_index(&self, node: &HVec<HK, Ix>) -> usize {
+        node.node.index()
+    }
+
+    fn neighbors<'a>(
+        &'a self,
+        node: &'a HVec<HK, Ix>,
+    ) -> impl Iterator<Item = (HVec<HK, Ix>, &'a K)> + 'a {
+        node.as_slice()
+            .iter()
+            .map(move |edge| (edge.neighbor.weak(), self.edge_get_key(edge)))
+    }
 }
 
-impl<M, K, V, HK, Strategy> Default for HggCore<M, K, V, HK, Strategy>
+impl<M, K, V, HK, Strategy, Ix> Default for HggCore<M, K, V, HK, Strategy, Ix>
 where
     M: Default,
+    Ix: NodeIndex,
 {
     fn default() -> Self {
         Self::new(M::default())
@@ -1105,64 +3451,99 @@ trait HggInternal {
     type K;
     type V;
     type HK;
-    fn make_edge_to_node(&self, node: &HVec<Self::HK>) -> HggEdge<Self::HK>;
-    fn edge_get_key<'a>(&'a self, edge: &'a HggEdge<Self::HK>) -> &'a Self::K;
-    fn node_get_key<'a>(&'a self, node: &'a HVec<Self::HK>) -> &'a Self::K;
+    type Ix: NodeIndex;
+    fn make_edge_to_node(&self, node: &HVec<Self::HK, Self::Ix>) -> HggEdge<Self::HK, Self::Ix>;
+    fn edge_get_key<'a>(&'a self, edge: &'a HggEdge<Self::HK, Self::Ix>) -> &'a Self::K;
+    fn node_get_key<'a>(&'a self, node: &'a HVec<Self::HK, Self::Ix>) -> &'a Self::K;
     fn add_node_layer(&mut self, node: usize);
+    /// Disconnects `node` from all of its neighbors on `layer`.
+    ///
+    /// Returns the old neighbors sorted by their distance to `node`.
+    fn disconnect_layer_neighbors(&mut self, layer: usize, node: usize) -> Vec<usize>;
+    /// Connects `node` to the nodes in `knn` on `layer` without searching for them first.
+    ///
+    /// `knn` must be sorted by distance to `node`.
+    fn connect_layer_neighborhood(&mut self, layer: usize, node: usize, knn: &[usize]);
+    /// `scratch` is the memory used by the search for the neighborhood.
     /// `layer` is the layer to optimize on.
     /// `node` is the node we are optimizing.
     /// `found` is the node we found that is closest to the target node `node`.
     /// `distance` is the distance of `found` from `node`.
     /// `reconnect` tells us if the node is already connected and needs to be disconnected before optimizing.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     fn optimize_layer_neighborhood(
         &mut self,
+        scratch: &mut Scratch<HVec<Self::HK, Self::Ix>, <Self::M as Metric<Self::K>>::Unit>,
         layer: usize,
         node: usize,
         found: usize,
         distance: <Self::M as Metric<Self::K>>::Unit,
         reconnect: bool,
+        visitor: &mut impl SearchVisitor<<Self::M as Metric<Self::K>>::Unit>,
     );
 }
 
-impl<M, K, V> HggInternal for HggCore<M, K, V, K, StrategyRegular>
+impl<M, K, V, Ix> HggInternal for HggCore<M, K, V, K, StrategyRegular, Ix>
 where
     M: Metric<K>,
     K: Clone,
+    Ix: NodeIndex,
 {
     type M = M;
     type K = K;
     type V = V;
     type HK = K;
+    type Ix = Ix;
 
-    fn make_edge_to_node(&self, node: &HVec<Self::HK>) -> HggEdge<Self::HK> {
+    fn make_edge_to_node(&self, node: &HVec<Self::HK, Self::Ix>) -> HggEdge<Self::HK, Self::Ix> {
         HggEdge {
             key: node.key.clone(),
             neighbor: node.weak(),
         }
     }
 
-    fn edge_get_key<'a>(&'a self, edge: &'a HggEdge<Self::HK>) -> &'a K {
+    fn edge_get_key<'a>(&'a self, edge: &'a HggEdge<Self::HK, Self::Ix>) -> &'a K {
         &edge.key
     }
 
-    fn node_get_key<'a>(&'a self, node: &'a HVec<Self::HK>) -> &'a K {
+    fn node_get_key<'a>(&'a self, node: &'a HVec<Self::HK, Self::Ix>) -> &'a K {
         &node.key
     }
 
     fn add_node_layer(&mut self, node: usize) {
         let key = self.nodes[node].key.clone();
-        self.nodes[node]
-            .layers
-            .push(HeaderVec::new(HggHeader { key, node }));
+        self.nodes[node].layers.push(HeaderVec::new(HggHeader {
+            key,
+            node: Ix::new(node),
+        }));
+    }
+
+    fn disconnect_layer_neighbors(&mut self, layer: usize, node: usize) -> Vec<usize> {
+        let mut node = self.layer_node_weak(layer, node);
+        self.disconnect_layer(layer, &mut node)
+            .into_iter()
+            .map(|(neighbor, _, _)| neighbor)
+            .collect()
+    }
+
+    fn connect_layer_neighborhood(&mut self, layer: usize, node: usize, knn: &[usize]) {
+        let mut node = self.layer_node_weak(layer, node);
+        let knn: Vec<(usize, K)> = knn
+            .iter()
+            .map(|&neighbor| (neighbor, self.nodes[neighbor].key.clone()))
+            .collect();
+        self.connect_layer_knn(layer, &mut node, &knn);
     }
 
     fn optimize_layer_neighborhood(
         &mut self,
+        scratch: &mut Scratch<HVec<Self::HK, Ix>, M::Unit>,
         layer: usize,
         node_id: usize,
         found: usize,
         distance: M::Unit,
         reconnect: bool,
+        visitor: &mut impl SearchVisitor<M::Unit>,
     ) {
         // Get the node's weak ref.
         let mut node = self.layer_node_weak(layer, node_id);
@@ -1170,14 +3551,15 @@ where
         // Do a knn search on this layer, starting at the found node.
         let mut knn: Vec<(usize, K)> = self
             .search_layer_knn_from_weak(
+                scratch,
                 self.layer_node_weak(layer, found),
                 distance,
                 &node.key,
                 self.insert_knn,
+                visitor,
             )
-            .into_iter()
             .skip(if reconnect { 1 } else { 0 })
-            .map(|(neighbor, _, _)| (neighbor.node, neighbor.key.clone()))
+            .map(|(neighbor, _)| (neighbor.node.index(), neighbor.key.clone()))
             .collect();
 
         // If we are reconnecting the node, we need to disconnect its edges first.
@@ -1198,6 +3580,63 @@ where
             }
         }
 
+        self.connect_layer_knn(layer, &mut node, &knn);
+
+        // Make sure we can still connect to the old neighbors.
+        for (old_neighbor, distance, old_key) in old_neighbors {
+            let (mut found, _) = self.search_layer(
+                self.layer_node_weak(layer, node_id),
+                distance,
+                &old_key,
+                &mut (),
+            );
+            if found.node.index() != old_neighbor {
+                self.layer_add_edge_dedup_weak(
+                    layer,
+                    &mut found,
+                    &mut self.layer_node_weak(layer, old_neighbor),
+                );
+            }
+        }
+    }
+}
+
+impl<M, K, V, Ix> HggCore<M, K, V, K, StrategyRegular, Ix>
+where
+    M: Metric<K>,
+    K: Clone,
+    Ix: NodeIndex,
+{
+    /// Internal function for disconnecting a node from the graph on the layer this HVec exists on.
+    ///
+    /// Returns nodes as usize because as nodes are re-added, it is possible that neighbors reallocate
+    /// and break the weak pointers.
+    ///
+    /// Returns (node, distance) pairs sorted by distance, which [`HggCore::unlink`] relies on to pick a new root.
+    fn disconnect_layer(
+        &mut self,
+        layer: usize,
+        node: &mut HVec<K, Ix>,
+    ) -> Vec<(usize, M::Unit, K)> {
+        let mut old_neighbors = Vec::with_capacity(node.len());
+        let node_key = node.key.clone();
+        let ptr = node.ptr();
+        self.edges[layer] -= node.len();
+        for HggEdge { neighbor, key } in node.as_mut_slice() {
+            let distance = self.metric.distance(&node_key, key);
+            let pos = old_neighbors.partition_point(|&(_, d, _)| d <= distance);
+            old_neighbors.insert(pos, (neighbor.node.index(), distance, key.clone()));
+            neighbor.retain(|HggEdge { neighbor, .. }| !neighbor.is(ptr));
+        }
+        node.retain(|_| false);
+        old_neighbors
+    }
+
+    /// Connects `node` to the nodes in `knn` on `layer`, adding edges such that every node in `knn`
+    /// can be reached from `node` by greedy search.
+    ///
+    /// `knn` must be sorted by distance to `node`.
+    fn connect_layer_knn(&mut self, layer: usize, node: &mut HVec<K, Ix>, knn: &[(usize, K)]) {
         // The initial neighbors only includes the edge we just added.
         let mut neighbors: Vec<K> = Vec::with_capacity(knn.len());
 
@@ -1210,7 +3649,7 @@ where
                 // In this case, add an edge (with dedup) between them to make sure there is a path.
                 self.layer_add_edge_dedup_weak(
                     layer,
-                    &mut node,
+                    node,
                     &mut self.layer_node_weak(layer, target_node),
                 );
                 knn_index += 1;
@@ -1232,11 +3671,8 @@ where
                 let nn_distance = self.metric.distance(&nn_key, &target_key);
                 // Add the node as a neighbor (closer or not).
                 // This will update the weak ref if necessary.
-                if self.layer_add_edge_dedup_weak(
-                    layer,
-                    &mut self.layer_node_weak(layer, nn),
-                    &mut node,
-                ) {
+                if self.layer_add_edge_dedup_weak(layer, &mut self.layer_node_weak(layer, nn), node)
+                {
                     neighbors.push(nn_key);
                 }
                 // Check if this node is closer to the target than `from`.
@@ -1250,89 +3686,64 @@ where
                 "we should always be able to connect to all the neighbors using themselves"
             );
         }
-
-        // Make sure we can still connect to the old neighbors.
-        for (old_neighbor, distance, old_key) in old_neighbors {
-            let (mut found, _) = self.search_layer_from_weak(
-                self.layer_node_weak(layer, node_id),
-                distance,
-                &old_key,
-            );
-            if found.node != old_neighbor {
-                self.layer_add_edge_dedup_weak(
-                    layer,
-                    &mut found,
-                    &mut self.layer_node_weak(layer, old_neighbor),
-                );
-            }
-        }
-    }
-}
-
-impl<M, K, V> HggCore<M, K, V, K, StrategyRegular>
-where
-    M: Metric<K>,
-    K: Clone,
-{
-    /// Internal function for disconnecting a node from the graph on the layer this HVec exists on.
-    ///
-    /// Returns nodes as usize because as nodes are re-added, it is possible that neighbors reallocate
-    /// and break the weak pointers.
-    ///
-    /// Returns (node, distance) pairs.
-    fn disconnect_layer(&mut self, layer: usize, node: &mut HVec<K>) -> Vec<(usize, M::Unit, K)> {
-        let mut old_neighbors = Vec::with_capacity(node.len());
-        let node_key = node.key.clone();
-        let ptr = node.ptr();
-        self.edges[layer] -= node.len();
-        for HggEdge { neighbor, key } in node.as_mut_slice() {
-            let distance = self.metric.distance(&node_key, key);
-            let pos = old_neighbors.partition_point(|&(_, d, _)| d <= distance);
-            old_neighbors.insert(pos, (neighbor.node, distance, key.clone()));
-            neighbor.retain(|HggEdge { neighbor, .. }| !neighbor.is(ptr));
-        }
-        node.retain(|_| false);
-        old_neighbors
     }
 }
 
-impl<M, K, V> HggInternal for HggCore<M, K, V, (), StrategyLite>
+impl<M, K, V, Ix> HggInternal for HggCore<M, K, V, (), StrategyLite, Ix>
 where
     M: Metric<K>,
+    Ix: NodeIndex,
 {
     type M = M;
     type K = K;
     type V = V;
     type HK = ();
+    type Ix = Ix;
 
-    fn make_edge_to_node(&self, node: &HVec<Self::HK>) -> HggEdge<Self::HK> {
+    fn make_edge_to_node(&self, node: &HVec<Self::HK, Self::Ix>) -> HggEdge<Self::HK, Self::Ix> {
         HggEdge {
             key: (),
             neighbor: node.weak(),
         }
     }
 
-    fn edge_get_key<'a>(&'a self, edge: &'a HggEdge<Self::HK>) -> &'a K {
-        &self.nodes[edge.neighbor.node].key
+    fn edge_get_key<'a>(&'a self, edge: &'a HggEdge<Self::HK, Self::Ix>) -> &'a K {
+        &self.nodes[edge.neighbor.node.index()].key
     }
 
-    fn node_get_key<'a>(&'a self, node: &'a HVec<Self::HK>) -> &'a K {
-        &self.nodes[node.node].key
+    fn node_get_key<'a>(&'a self, node: &'a HVec<Self::HK, Self::Ix>) -> &'a K {
+        &self.nodes[node.node.index()].key
     }
 
     fn add_node_layer(&mut self, node: usize) {
-        self.nodes[node]
-            .layers
-            .push(HeaderVec::new(HggHeader { key: (), node }));
+        self.nodes[node].layers.push(HeaderVec::new(HggHeader {
+            key: (),
+            node: Ix::new(node),
+        }));
+    }
+
+    fn disconnect_layer_neighbors(&mut self, layer: usize, node: usize) -> Vec<usize> {
+        let mut node = self.layer_node_weak(layer, node);
+        self.disconnect_layer(layer, &mut node)
+            .into_iter()
+            .map(|(neighbor, _)| neighbor)
+            .collect()
+    }
+
+    fn connect_layer_neighborhood(&mut self, layer: usize, node: usize, knn: &[usize]) {
+        let mut node = self.layer_node_weak(layer, node);
+        self.connect_layer_knn(layer, &mut node, knn);
     }
 
     fn optimize_layer_neighborhood(
         &mut self,
+        scratch: &mut Scratch<HVec<Self::HK, Ix>, M::Unit>,
         layer: usize,
         node_id: usize,
         found: usize,
         distance: M::Unit,
         reconnect: bool,
+        visitor: &mut impl SearchVisitor<M::Unit>,
     ) {
         // Get the node's weak ref.
         let mut node = self.layer_node_weak(layer, node_id);
@@ -1340,14 +3751,15 @@ where
         // Do a knn search on this layer, starting at the found node.
         let mut knn: Vec<usize> = self
             .search_layer_knn_from_weak(
+                scratch,
                 self.layer_node_weak(layer, found),
                 distance,
                 &self.nodes[node_id].key,
                 self.insert_knn,
+                visitor,
             )
-            .into_iter()
             .skip(if reconnect { 1 } else { 0 })
-            .map(|(neighbor, _, _)| neighbor.node)
+            .map(|(neighbor, _)| neighbor.node.index())
             .collect();
 
         // If we are reconnecting the node, we need to disconnect its edges first.
@@ -1360,7 +3772,7 @@ where
         // Add the old neighbors to the knn.
         for &(old_neighbor, distance) in &old_neighbors {
             // Check if it is not contained in the knn.
-            if !knn.iter().any(|&kn| kn == old_neighbor) {
+            if !knn.contains(&old_neighbor) {
                 // In this case, add it to the correct spot in the knn.
                 let pos = knn.partition_point(|&kn| {
                     self.metric
@@ -1371,6 +3783,62 @@ where
             }
         }
 
+        self.connect_layer_knn(layer, &mut node, &knn);
+
+        // Make sure we can still connect to the old neighbors.
+        for (old_neighbor, distance) in old_neighbors {
+            let (mut found, _) = self.search_layer(
+                self.layer_node_weak(layer, node_id),
+                distance,
+                &self.nodes[old_neighbor].key,
+                &mut (),
+            );
+            if found.node.index() != old_neighbor {
+                self.layer_add_edge_dedup_weak(
+                    layer,
+                    &mut found,
+                    &mut self.layer_node_weak(layer, old_neighbor),
+                );
+            }
+        }
+    }
+}
+
+impl<M, K, V, Ix> HggCore<M, K, V, (), StrategyLite, Ix>
+where
+    M: Metric<K>,
+    Ix: NodeIndex,
+{
+    /// Internal function for disconnecting a node from the graph on the layer this HVec exists on.
+    ///
+    /// Returns nodes as usize because as nodes are re-added, it is possible that neighbors reallocate
+    /// and break the weak pointers.
+    ///
+    /// Returns (node, distance) pairs sorted by distance, which [`HggCore::unlink`] relies on to pick a new root.
+    fn disconnect_layer(&mut self, layer: usize, node: &mut HVec<(), Ix>) -> Vec<(usize, M::Unit)> {
+        let mut old_neighbors: Vec<(usize, M::Unit)> = Vec::with_capacity(node.len());
+        let ptr = node.ptr();
+        self.edges[layer] -= node.len();
+        let node_index = node.node.index();
+        for HggEdge { neighbor, .. } in node.as_mut_slice() {
+            let distance = self.metric.distance(
+                &self.nodes[neighbor.node.index()].key,
+                &self.nodes[node_index].key,
+            );
+            let pos = old_neighbors.partition_point(|&(_, d)| d <= distance);
+            old_neighbors.insert(pos, (neighbor.node.index(), distance));
+            neighbor.retain(|HggEdge { neighbor, .. }| !neighbor.is(ptr));
+        }
+        node.retain(|_| false);
+        old_neighbors
+    }
+
+    /// Connects `node` to the nodes in `knn` on `layer`, adding edges such that every node in `knn`
+    /// can be reached from `node` by greedy search.
+    ///
+    /// `knn` must be sorted by distance to `node`.
+    fn connect_layer_knn(&mut self, layer: usize, node: &mut HVec<(), Ix>, knn: &[usize]) {
+        let node_id = node.node.index();
         // The initial neighbors only includes the edge we just added.
         let mut neighbors: Vec<usize> = Vec::with_capacity(knn.len());
 
@@ -1385,7 +3853,7 @@ where
                 // In this case, add an edge (with dedup) between them to make sure there is a path.
                 self.layer_add_edge_dedup_weak(
                     layer,
-                    &mut node,
+                    node,
                     &mut self.layer_node_weak(layer, target_node),
                 );
                 knn_index += 1;
@@ -1410,11 +3878,8 @@ where
                     .distance(&self.nodes[nn].key, &self.nodes[target_node].key);
                 // Add the node as a neighbor (closer or not).
                 // This will update the weak ref if necessary.
-                if self.layer_add_edge_dedup_weak(
-                    layer,
-                    &mut self.layer_node_weak(layer, nn),
-                    &mut node,
-                ) {
+                if self.layer_add_edge_dedup_weak(layer, &mut self.layer_node_weak(layer, nn), node)
+                {
                     neighbors.push(nn);
                 }
                 // Check if this node is closer to the target than `from`.
@@ -1428,49 +3893,5 @@ where
                 "we should always be able to connect to all the neighbors using themselves"
             );
         }
-
-        // Make sure we can still connect to the old neighbors.
-        for (old_neighbor, distance) in old_neighbors {
-            let (mut found, _) = self.search_layer_from_weak(
-                self.layer_node_weak(layer, node_id),
-                distance,
-                &self.nodes[old_neighbor].key,
-            );
-            if found.node != old_neighbor {
-                self.layer_add_edge_dedup_weak(
-                    layer,
-                    &mut found,
-                    &mut self.layer_node_weak(layer, old_neighbor),
-                );
-            }
-        }
-    }
-}
-
-impl<M, K, V> HggCore<M, K, V, (), StrategyLite>
-where
-    M: Metric<K>,
-{
-    /// Internal function for disconnecting a node from the graph on the layer this HVec exists on.
-    ///
-    /// Returns nodes as usize because as nodes are re-added, it is possible that neighbors reallocate
-    /// and break the weak pointers.
-    ///
-    /// Returns (node, distance) pairs.
-    fn disconnect_layer(&mut self, layer: usize, node: &mut HVec<()>) -> Vec<(usize, M::Unit)> {
-        let mut old_neighbors: Vec<(usize, M::Unit)> = Vec::with_capacity(node.len());
-        let ptr = node.ptr();
-        self.edges[layer] -= node.len();
-        let node_index = node.node;
-        for HggEdge { neighbor, .. } in node.as_mut_slice() {
-            let distance = self
-                .metric
-                .distance(&self.nodes[neighbor.node].key, &self.nodes[node_index].key);
-            let pos = old_neighbors.partition_point(|&(_, d)| d <= distance);
-            old_neighbors.insert(pos, (neighbor.node, distance));
-            neighbor.retain(|HggEdge { neighbor, .. }| !neighbor.is(ptr));
-        }
-        node.retain(|_| false);
-        old_neighbors
     }
 }
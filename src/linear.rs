@@ -0,0 +1,203 @@
+use crate::{Hgg, HggLite, NodeIndex};
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use space::{Knn, KnnInsert, KnnMap, KnnPoints, Metric, Neighbor};
+
+/// A collection which can find the exact nearest neighbors of a query by computing the distance to every key it contains.
+///
+/// This is the ground truth that the approximate searches of a collection are compared against by
+/// [`evaluate_recall`](crate::evaluate_recall).
+pub trait ExhaustiveKnn: Knn {
+    /// Finds the exact `num` nearest neighbors of the query using linear search.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    #[allow(clippy::type_complexity)]
+    fn exhaustive_knn(
+        &self,
+        query: &Self::Point,
+        num: usize,
+    ) -> Vec<Neighbor<<Self::Metric as Metric<Self::Point>>::Unit, Self::Ix>>;
+}
+
+/// Finds the `num` nearest of the `(index, key)` pairs to the query.
+///
+/// Neighbors at the same distance are sorted by index.
+fn linear_knn<'a, M, K: 'a>(
+    metric: &M,
+    keys: impl Iterator<Item = (usize, &'a K)>,
+    query: &K,
+    num: usize,
+) -> Vec<Neighbor<M::Unit>>
+where
+    M: Metric<K>,
+{
+    let mut neighbors: Vec<Neighbor<M::Unit>> = Vec::with_capacity(num);
+    if num == 0 {
+        return neighbors;
+    }
+    for (index, key) in keys {
+        let distance = metric.distance(query, key);
+        // Skip the neighbor if it is no closer than the furthest of `num` neighbors.
+        if neighbors.len() == num && distance >= neighbors[num - 1].distance {
+            continue;
+        }
+        let pos = neighbors.partition_point(|n| n.distance <= distance);
+        if neighbors.len() == num {
+            neighbors.pop();
+        }
+        neighbors.insert(pos, Neighbor { index, distance });
+    }
+    neighbors
+}
+
+/// A collection that finds the exact nearest neighbors of a query by computing the distance to every key.
+///
+/// Searching takes time linear in the size of the collection, so this is only practical for small collections.
+/// It implements the same `space` traits as [`Hgg`] and [`HggLite`], so it can be used as a baseline to
+/// compare them against, or in place of them when the collection is small enough.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinearKnn<M, K, V> {
+    metric: M,
+    keys: Vec<K>,
+    values: Vec<V>,
+}
+
+impl<M, K, V> LinearKnn<M, K, V> {
+    /// Creates a new empty [`LinearKnn`].
+    pub fn new(metric: M) -> Self {
+        Self {
+            metric,
+            keys: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Get the (key, value) pair of an entry.
+    pub fn get(&self, index: usize) -> Option<(&K, &V)> {
+        Some((self.keys.get(index)?, &self.values[index]))
+    }
+
+    /// Iterates over the (index, key, value) of every entry in order of index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &K, &V)> + '_ {
+        self.keys
+            .iter()
+            .zip(&self.values)
+            .enumerate()
+            .map(|(index, (key, value))| (index, key, value))
+    }
+
+    /// Iterates over the keys of every entry in order of index.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.keys.iter()
+    }
+
+    /// Iterates over the values of every entry in order of index.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.values.iter()
+    }
+
+    /// Checks if the collection is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the number of entries in the collection.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl<M, K, V> Default for LinearKnn<M, K, V>
+where
+    M: Default,
+{
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<M, K, V> Knn for LinearKnn<M, K, V>
+where
+    M: Metric<K>,
+{
+    type Ix = usize;
+    type Metric = M;
+    type Point = K;
+    type KnnIter = Vec<Neighbor<M::Unit>>;
+
+    fn knn(&self, query: &K, num: usize) -> Self::KnnIter {
+        linear_knn(&self.metric, self.keys.iter().enumerate(), query, num)
+    }
+}
+
+impl<M, K, V> KnnPoints for LinearKnn<M, K, V>
+where
+    M: Metric<K>,
+{
+    fn get_point(&self, index: usize) -> &'_ K {
+        &self.keys[index]
+    }
+}
+
+impl<M, K, V> KnnMap for LinearKnn<M, K, V>
+where
+    M: Metric<K>,
+{
+    type Value = V;
+    fn get_value(&self, index: usize) -> &'_ V {
+        &self.values[index]
+    }
+}
+
+impl<M, K, V> KnnInsert for LinearKnn<M, K, V>
+where
+    M: Metric<K>,
+{
+    fn insert(&mut self, key: K, value: V) -> usize {
+        self.keys.push(key);
+        self.values.push(value);
+        self.keys.len() - 1
+    }
+}
+
+impl<M, K, V> ExhaustiveKnn for LinearKnn<M, K, V>
+where
+    M: Metric<K>,
+{
+    fn exhaustive_knn(&self, query: &K, num: usize) -> Vec<Neighbor<M::Unit>> {
+        self.knn(query, num)
+    }
+}
+
+impl<M, K, V, Ix> ExhaustiveKnn for Hgg<M, K, V, Ix>
+where
+    M: Metric<K>,
+    K: Clone,
+    Ix: NodeIndex,
+{
+    /// Nodes marked as deleted are not returned, just like [`Knn::knn`].
+    fn exhaustive_knn(&self, query: &K, num: usize) -> Vec<Neighbor<M::Unit>> {
+        let keys = self
+            .keys()
+            .enumerate()
+            .filter(|&(node, _)| !self.is_deleted(node));
+        linear_knn(&self.hgg.metric, keys, query, num)
+    }
+}
+
+impl<M, K, V, Ix> ExhaustiveKnn for HggLite<M, K, V, Ix>
+where
+    M: Metric<K>,
+    Ix: NodeIndex,
+{
+    /// Nodes marked as deleted are not returned, just like [`Knn::knn`].
+    fn exhaustive_knn(&self, query: &K, num: usize) -> Vec<Neighbor<M::Unit>> {
+        let keys = self
+            .keys()
+            .enumerate()
+            .filter(|&(node, _)| !self.is_deleted(node));
+        linear_knn(&self.hgg.metric, keys, query, num)
+    }
+}
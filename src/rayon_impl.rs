@@ -0,0 +1,145 @@
+use crate::{search::Scratch, Hgg, HggLite, NodeIndex};
+use alloc::vec::Vec;
+use core::ops::Range;
+use rayon::prelude::*;
+use space::{Knn, Metric, Neighbor};
+
+impl<M, K, V> Hgg<M, K, V>
+where
+    M: Metric<K> + Sync,
+    M::Unit: Send,
+    K: Clone + Sync,
+    V: Sync,
+{
+    /// Creates a new [`Hgg`] with default settings from a batch of (key, value) pairs in parallel using [`rayon`].
+    ///
+    /// The resulting graph is identical to the one created by [`Hgg::from_batch`].
+    pub fn par_from_batch(metric: M, entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut hgg = Self::new(metric);
+        hgg.par_insert_batch(entries);
+        hgg
+    }
+}
+
+impl<M, K, V, Ix> Hgg<M, K, V, Ix>
+where
+    M: Metric<K> + Sync,
+    M::Unit: Send,
+    K: Clone + Sync,
+    V: Sync,
+    Ix: NodeIndex,
+{
+    /// Finds the `num` nearest neighbors of every query in parallel using [`rayon`].
+    ///
+    /// Returns the neighbors of each query in the same order as the queries.
+    pub fn knn_batch(&self, queries: &[K], num: usize) -> Vec<Vec<Neighbor<M::Unit>>> {
+        queries
+            .par_iter()
+            .map_init(
+                || Scratch::with_visited(self.hgg.visited),
+                |scratch, query| {
+                    self.hgg
+                        .search_knn(scratch, query, num, &self.hgg.search_params(), &mut ())
+                        .into_iter()
+                        .map(|(index, distance)| Neighbor { index, distance })
+                        .collect()
+                },
+            )
+            .collect()
+    }
+
+    /// Finds the nearest neighbor of every query in parallel using [`rayon`].
+    ///
+    /// Returns the neighbor of each query in the same order as the queries.
+    pub fn nn_batch(&self, queries: &[K]) -> Vec<Option<Neighbor<M::Unit>>> {
+        queries.par_iter().map(|query| self.nn(query)).collect()
+    }
+
+    /// Inserts a batch of (key, value) pairs, searching for their neighbors in parallel using [`rayon`].
+    ///
+    /// The resulting graph is identical to the one created by [`Hgg::insert_batch`].
+    /// Only the searches for the bottom layer neighbors run in parallel. Linking the nodes into the graph,
+    /// adding them to higher layers, and freshening run serially, so they bound the speedup.
+    ///
+    /// Returns the range of indices of the inserted nodes.
+    pub fn par_insert_batch(&mut self, entries: impl IntoIterator<Item = (K, V)>) -> Range<usize> {
+        self.hgg.insert_batch_with_search(entries, |hgg, chunk| {
+            chunk
+                .par_iter()
+                .map_init(Scratch::new, |scratch, (key, _)| {
+                    hgg.search_insert_knn(scratch, key)
+                })
+                .collect()
+        })
+    }
+}
+
+impl<M, K, V> HggLite<M, K, V>
+where
+    M: Metric<K> + Sync,
+    M::Unit: Send,
+    K: Sync,
+    V: Sync,
+{
+    /// Creates a new [`HggLite`] with default settings from a batch of (key, value) pairs in parallel using [`rayon`].
+    ///
+    /// The resulting graph is identical to the one created by [`HggLite::from_batch`].
+    pub fn par_from_batch(metric: M, entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut hgg = Self::new(metric);
+        hgg.par_insert_batch(entries);
+        hgg
+    }
+}
+
+impl<M, K, V, Ix> HggLite<M, K, V, Ix>
+where
+    M: Metric<K> + Sync,
+    M::Unit: Send,
+    K: Sync,
+    V: Sync,
+    Ix: NodeIndex,
+{
+    /// Finds the `num` nearest neighbors of every query in parallel using [`rayon`].
+    ///
+    /// Returns the neighbors of each query in the same order as the queries.
+    pub fn knn_batch(&self, queries: &[K], num: usize) -> Vec<Vec<Neighbor<M::Unit>>> {
+        queries
+            .par_iter()
+            .map_init(
+                || Scratch::with_visited(self.hgg.visited),
+                |scratch, query| {
+                    self.hgg
+                        .search_knn(scratch, query, num, &self.hgg.search_params(), &mut ())
+                        .into_iter()
+                        .map(|(index, distance)| Neighbor { index, distance })
+                        .collect()
+                },
+            )
+            .collect()
+    }
+
+    /// Finds the nearest neighbor of every query in parallel using [`rayon`].
+    ///
+    /// Returns the neighbor of each query in the same order as the queries.
+    pub fn nn_batch(&self, queries: &[K]) -> Vec<Option<Neighbor<M::Unit>>> {
+        queries.par_iter().map(|query| self.nn(query)).collect()
+    }
+
+    /// Inserts a batch of (key, value) pairs, searching for their neighbors in parallel using [`rayon`].
+    ///
+    /// The resulting graph is identical to the one created by [`HggLite::insert_batch`].
+    /// Only the searches for the bottom layer neighbors run in parallel. Linking the nodes into the graph,
+    /// adding them to higher layers, and freshening run serially, so they bound the speedup.
+    ///
+    /// Returns the range of indices of the inserted nodes.
+    pub fn par_insert_batch(&mut self, entries: impl IntoIterator<Item = (K, V)>) -> Range<usize> {
+        self.hgg.insert_batch_with_search(entries, |hgg, chunk| {
+            chunk
+                .par_iter()
+                .map_init(Scratch::new, |scratch, (key, _)| {
+                    hgg.search_insert_knn(scratch, key)
+                })
+                .collect()
+        })
+    }
+}
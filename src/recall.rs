@@ -0,0 +1,100 @@
+extern crate std;
+
+use crate::ExhaustiveKnn;
+use alloc::vec::Vec;
+use num_traits::ToPrimitive;
+use space::Metric;
+use std::time::Instant;
+
+/// The quality and speed of the approximate searches of a collection, as measured by [`evaluate_recall`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RecallReport {
+    /// The fraction of the exact `k` nearest neighbors that were found, averaged over every query.
+    ///
+    /// An approximate neighbor counts as found if it is no further than the `k`th exact nearest neighbor,
+    /// so that neighbors at the same distance are interchangeable.
+    pub recall: f64,
+    /// The sum of the distances to the approximate neighbors divided by the sum of the distances to the same number
+    /// of exact nearest neighbors, averaged over every query. This is `1.0` when the search is exact.
+    ///
+    /// When the exact neighbors of a query are all at distance zero, its ratio is `1.0` if the approximate ones are
+    /// too. Otherwise its ratio is undefined, so the query is left out of the average and only lowers
+    /// [`Self::recall`]. If every query is left out, this is infinite.
+    pub distance_ratio: f64,
+    /// The number of approximate searches performed per second, counting only the queries which were not skipped.
+    pub queries_per_second: f64,
+}
+
+/// Compares the approximate `k` nearest neighbors of every query against the exact ones found by brute force.
+///
+/// Queries for which there are no neighbors (because the collection is empty or `k` is zero) are skipped.
+/// Only the approximate searches of the queries which are not skipped are timed for
+/// [`RecallReport::queries_per_second`].
+///
+/// Returns `None` if every query was skipped, including when there are no queries.
+///
+/// This requires the `std` feature.
+pub fn evaluate_recall<I>(index: &I, queries: &[I::Point], k: usize) -> Option<RecallReport>
+where
+    I: ExhaustiveKnn,
+    <I::Metric as Metric<I::Point>>::Unit: ToPrimitive,
+{
+    // Find the exact neighbors first, so that only the queries which aren't skipped are timed.
+    let exact: Vec<(&I::Point, Vec<_>)> = queries
+        .iter()
+        .map(|query| (query, index.exhaustive_knn(query, k)))
+        .filter(|(_, exact)| !exact.is_empty())
+        .collect();
+    if exact.is_empty() {
+        return None;
+    }
+
+    let start_time = Instant::now();
+    let approximate: Vec<Vec<_>> = exact
+        .iter()
+        .map(|&(query, _)| index.knn(query, k).into_iter().collect())
+        .collect();
+    let seconds = start_time.elapsed().as_secs_f64();
+
+    let mut recall = 0.0;
+    let mut distance_ratio = 0.0;
+    // The number of queries whose distance ratio is defined.
+    let mut ratios = 0;
+    for ((_, exact), approximate) in exact.iter().zip(approximate) {
+        let furthest = exact.last().unwrap().distance;
+        let found = approximate
+            .iter()
+            .filter(|neighbor| neighbor.distance <= furthest)
+            .count();
+        recall += found as f64 / exact.len() as f64;
+
+        let approximate_sum: f64 = approximate
+            .iter()
+            .map(|neighbor| neighbor.distance.to_f64().unwrap())
+            .sum();
+        let exact_sum: f64 = exact
+            .iter()
+            .take(approximate.len())
+            .map(|neighbor| neighbor.distance.to_f64().unwrap())
+            .sum();
+        if exact_sum != 0.0 {
+            distance_ratio += approximate_sum / exact_sum;
+            ratios += 1;
+        } else if approximate_sum == 0.0 {
+            // Finding only exact matches is a perfect result, even though the ratio is undefined.
+            distance_ratio += 1.0;
+            ratios += 1;
+        }
+    }
+
+    let evaluated = exact.len();
+    Some(RecallReport {
+        recall: recall / evaluated as f64,
+        distance_ratio: if ratios == 0 {
+            f64::INFINITY
+        } else {
+            distance_ratio / ratios as f64
+        },
+        queries_per_second: evaluated as f64 / seconds,
+    })
+}
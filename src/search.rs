@@ -0,0 +1,747 @@
+use crate::{HVec, SearchParams, VisitedSet};
+use ahash::RandomState;
+use alloc::{
+    collections::{BTreeMap, BinaryHeap},
+    vec,
+    vec::Vec,
+};
+use core::{
+    cmp::{Ordering, Reverse},
+    fmt,
+    hash::Hash,
+};
+use hashbrown::HashSet;
+use space::Metric;
+
+/// The distance unit of the metric of a [`SearchGraph`].
+pub(crate) type Unit<G> = <<G as SearchGraph>::M as Metric<<G as SearchGraph>::K>>::Unit;
+
+/// Receives the events of a search, which allows the path taken by the search to be traced and the work it does to be measured.
+///
+/// Every method does nothing by default, so only the events of interest need to be implemented.
+/// `()` is the visitor which ignores every event, and searches which use it cost nothing extra.
+///
+/// Nodes are identified by their index and `Unit` is the unit of distance of the metric.
+pub trait SearchVisitor<Unit> {
+    /// The search moved to `layer`. Searches begin on the top layer and descend one layer at a time.
+    fn enter_layer(&mut self, _layer: usize) {}
+    /// The distance from `node` to the query was computed.
+    fn distance(&mut self, _node: usize, _distance: Unit) {}
+    /// Greedy search moved to `node`, as it is closer to the query than the previous node on the current layer.
+    fn hop(&mut self, _node: usize, _distance: Unit) {}
+    /// The neighbors of `node` are about to be searched by kNN search.
+    fn expand(&mut self, _node: usize) {}
+    /// `node` was inserted into the pool of candidates of the kNN search.
+    fn pool_insert(&mut self, _node: usize, _distance: Unit) {}
+    /// `node` was evicted from the pool of candidates because enough closer candidates were found.
+    fn pool_evict(&mut self, _node: usize, _distance: Unit) {}
+    /// The kNN search on the current layer finished with `size` candidates in its pool.
+    fn pool_finish(&mut self, _size: usize) {}
+    /// Asked after every distance computation and before every step of the search, including the greedy search
+    /// through the upper layers. Returning `true` stops the search, which then returns the best nodes it has found
+    /// so far. Once it returns `true`, it must keep doing so.
+    fn stop(&mut self) -> bool {
+        false
+    }
+}
+
+impl<Unit> SearchVisitor<Unit> for () {}
+
+/// Scratch space for kNN searches which can be reused between searches to avoid allocating memory for each one.
+///
+/// Pass the same context to every call of [`Hgg::knn_in`](crate::Hgg::knn_in) on a thread, and its memory will be
+/// cleared before each search instead of being allocated again. `K`, `Unit` and `Ix` are the key type, the unit of
+/// distance of the metric, and the node index type of the graphs it is used with. It keeps the memory of an
+/// [`Hgg`](crate::Hgg) and an [`HggLite`](crate::HggLite) separately, so a context can be used with both.
+pub struct SearchContext<K, Unit, Ix = usize> {
+    hgg: Option<Scratch<HVec<K, Ix>, Unit>>,
+    lite: Option<Scratch<HVec<(), Ix>, Unit>>,
+}
+
+impl<K, Unit, Ix> SearchContext<K, Unit, Ix> {
+    /// Creates an empty [`SearchContext`]. It grows to fit the largest search it is used for.
+    pub fn new() -> Self {
+        Self {
+            hgg: None,
+            lite: None,
+        }
+    }
+
+    /// Gets the scratch space for an [`Hgg`](crate::Hgg), which keeps track of the nodes it visits with `visited`.
+    pub(crate) fn hgg_scratch(&mut self, visited: VisitedSet) -> &mut Scratch<HVec<K, Ix>, Unit> {
+        let scratch = self.hgg.get_or_insert_with(Scratch::new);
+        scratch.visited = visited;
+        scratch
+    }
+
+    /// Gets the scratch space for an [`HggLite`](crate::HggLite), which keeps track of the nodes it visits with
+    /// `visited`.
+    pub(crate) fn lite_scratch(&mut self, visited: VisitedSet) -> &mut Scratch<HVec<(), Ix>, Unit> {
+        let scratch = self.lite.get_or_insert_with(Scratch::new);
+        scratch.visited = visited;
+        scratch
+    }
+}
+
+impl<K, Unit, Ix> Default for SearchContext<K, Unit, Ix> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, Unit, Ix> fmt::Debug for SearchContext<K, Unit, Ix> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchContext")
+            .field("hgg", &self.hgg.is_some())
+            .field("lite", &self.lite.is_some())
+            .finish()
+    }
+}
+
+/// A node in the pool of candidates of a kNN search.
+///
+/// Entries are ordered by distance, and then by the order they were added to the pool in.
+#[derive(Clone)]
+struct PoolEntry<Node, Unit> {
+    node: Node,
+    distance: Unit,
+    /// The number of entries added to the pool before this one.
+    order: usize,
+    /// If the node passed the filter.
+    accepted: bool,
+}
+
+impl<Node, Unit: Ord> PoolEntry<Node, Unit> {
+    fn key(&self) -> (&Unit, usize) {
+        (&self.distance, self.order)
+    }
+}
+
+impl<Node, Unit: Ord> PartialEq for PoolEntry<Node, Unit> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<Node, Unit: Ord> Eq for PoolEntry<Node, Unit> {}
+
+impl<Node, Unit: Ord> PartialOrd for PoolEntry<Node, Unit> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Node, Unit: Ord> Ord for PoolEntry<Node, Unit> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The memory used by kNN search on a graph whose nodes are `Node`.
+pub(crate) struct Scratch<Node, Unit> {
+    /// The pool of candidates, with the worst entry on top.
+    pool: BinaryHeap<PoolEntry<Node, Unit>>,
+    /// The entries of the pool which have not been searched yet, with the best entry on top.
+    /// Entries evicted from the pool are left here until they reach the top.
+    unsearched: BinaryHeap<Reverse<PoolEntry<Node, Unit>>>,
+    /// The accepted entries of the pool once the search finishes, sorted from nearest to furthest.
+    bests: Vec<PoolEntry<Node, Unit>>,
+    /// This set is used to more quickly determine if a node is contained in the best set.
+    exclude: HashSet<Node, RandomState>,
+    /// How the search keeps track of the nodes it has visited.
+    visited: VisitedSet,
+    /// The last search which visited each node, used instead of `exclude` with [`VisitedSet::Epoch`].
+    epochs: Vec<u32>,
+    /// The number of the current search. A node is visited if its entry in `epochs` is equal to this.
+    epoch: u32,
+}
+
+// SAFETY: The nodes left in the scratch space after a search are only compared and hashed, never dereferenced,
+// and every search clears them before it begins.
+unsafe impl<Node, Unit> Send for Scratch<Node, Unit> where Unit: Send {}
+
+impl<Node, Unit> Scratch<Node, Unit> {
+    pub(crate) fn new() -> Self {
+        Self::with_visited(VisitedSet::Hash)
+    }
+
+    pub(crate) fn with_visited(visited: VisitedSet) -> Self {
+        Self {
+            visited,
+            pool: BinaryHeap::new(),
+            unsearched: BinaryHeap::new(),
+            bests: Vec::new(),
+            exclude: HashSet::with_hasher(RandomState::with_seeds(0, 0, 0, 0)),
+            epochs: Vec::new(),
+            epoch: 0,
+        }
+    }
+
+    /// Forgets every visited node to begin a search on a graph with `len` nodes.
+    fn begin(&mut self, len: usize, capacity: usize)
+    where
+        Node: Eq + Hash,
+    {
+        self.pool.clear();
+        self.unsearched.clear();
+        self.bests.clear();
+        match self.visited {
+            VisitedSet::Hash => {
+                self.exclude.clear();
+                self.exclude.reserve(capacity);
+            }
+            VisitedSet::Epoch => {
+                // Nodes added since the last search have never been visited.
+                self.epochs.resize(len, 0);
+                self.epoch = self.epoch.wrapping_add(1);
+                // Once the epoch wraps around, old entries could match again, so they must be cleared.
+                if self.epoch == 0 {
+                    self.epochs.iter_mut().for_each(|epoch| *epoch = 0);
+                    self.epoch = 1;
+                }
+            }
+        }
+    }
+
+    /// Checks if a node was visited. `index` is only called with [`VisitedSet::Epoch`].
+    fn is_visited(&self, node: &Node, index: impl FnOnce() -> usize) -> bool
+    where
+        Node: Eq + Hash,
+    {
+        match self.visited {
+            VisitedSet::Hash => self.exclude.contains(node),
+            VisitedSet::Epoch => self.epochs[index()] == self.epoch,
+        }
+    }
+
+    /// Marks a node as visited. `index` is only called with [`VisitedSet::Epoch`].
+    fn visit(&mut self, node: Node, index: impl FnOnce() -> usize)
+    where
+        Node: Eq + Hash,
+    {
+        match self.visited {
+            VisitedSet::Hash => {
+                self.exclude.insert(node);
+            }
+            VisitedSet::Epoch => self.epochs[index()] = self.epoch,
+        }
+    }
+
+    /// Marks a node as not visited. `index` is only called with [`VisitedSet::Epoch`].
+    fn unvisit(&mut self, node: &Node, index: impl FnOnce() -> usize)
+    where
+        Node: Eq + Hash,
+    {
+        match self.visited {
+            VisitedSet::Hash => {
+                self.exclude.remove(node);
+            }
+            // The epoch is never zero.
+            VisitedSet::Epoch => self.epochs[index()] = 0,
+        }
+    }
+
+    /// Takes the `(node, distance)` pairs found by the last kNN search, sorted from nearest to furthest.
+    pub(crate) fn drain_neighbors(&mut self) -> impl Iterator<Item = (Node, Unit)> + '_ {
+        self.bests
+            .drain(..)
+            .map(|PoolEntry { node, distance, .. }| (node, distance))
+    }
+}
+
+/// Read-only access to the layers of a graph, which is all that the search algorithms need.
+///
+/// This allows the same search algorithms to run on the graph in memory and on flat views of it.
+pub(crate) trait SearchGraph {
+    type M: Metric<Self::K>;
+    type K;
+    /// Refers to a node on one specific layer of the graph.
+    type Node: Clone + Eq + Hash;
+
+    fn metric(&self) -> &Self::M;
+
+    /// Returns the node on the top layer, or `None` if the graph is empty.
+    fn root(&self) -> Option<usize>;
+
+    /// Returns the number of layers in the graph.
+    fn layers(&self) -> usize;
+
+    /// Returns the number of nodes in the graph.
+    fn len(&self) -> usize;
+
+    fn key(&self, node: usize) -> &Self::K;
+
+    fn layer_node(&self, layer: usize, node: usize) -> Self::Node;
+
+    fn node_index(&self, node: &Self::Node) -> usize;
+
+    /// Returns the neighbors of `node` on its layer along with their keys.
+    fn neighbors<'a>(
+        &'a self,
+        node: &'a Self::Node,
+    ) -> impl Iterator<Item = (Self::Node, &'a Self::K)> + 'a;
+
+    fn best_neighbor_distance(
+        &self,
+        node: &Self::Node,
+        query: &Self::K,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) -> Option<(Self::Node, Unit<Self>)> {
+        let mut best: Option<(Self::Node, Unit<Self>)> = None;
+        for (neighbor, key) in self.neighbors(node) {
+            let distance = self.metric().distance(key, query);
+            visitor.distance(self.node_index(&neighbor), distance);
+            // Keep the first of the neighbors with the smallest distance.
+            if best
+                .as_ref()
+                .is_none_or(|&(_, best_distance)| distance < best_distance)
+            {
+                best = Some((neighbor, distance));
+            }
+            if visitor.stop() {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Finds the nearest neighbor to the query key starting from the `from` node using greedy search.
+    ///
+    /// Returns `(node, distance)`.
+    fn search_layer(
+        &self,
+        from: Self::Node,
+        from_distance: Unit<Self>,
+        query: &Self::K,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) -> (Self::Node, Unit<Self>) {
+        let mut best = from;
+        let mut best_distance = from_distance;
+
+        while !visitor.stop() {
+            match self.best_neighbor_distance(&best, query, visitor) {
+                Some((neighbor, distance)) if distance < best_distance => {
+                    visitor.hop(self.node_index(&neighbor), distance);
+                    best = neighbor;
+                    best_distance = distance;
+                }
+                _ => break,
+            }
+        }
+        (best, best_distance)
+    }
+
+    /// Searches for the nearest neighbor greedily from the top layer to the bottom.
+    ///
+    /// This is faster than calling [`SearchGraph::search_knn_filtered`] with `num` of `1`.
+    ///
+    /// Returns `(node, distance)`.
+    fn search_to_layer(
+        &self,
+        final_layer: usize,
+        query: &Self::K,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) -> Option<(usize, Unit<Self>)> {
+        let mut node = self.root()?;
+        visitor.enter_layer(self.layers() - 1);
+        let mut distance = self.metric().distance(self.key(node), query);
+        visitor.distance(node, distance);
+        // This assumes that the top layer only contains one node (as it should).
+        for layer in (final_layer..self.layers() - 1).rev() {
+            visitor.enter_layer(layer);
+            let (new_node, new_distance) =
+                self.search_layer(self.layer_node(layer, node), distance, query, visitor);
+            node = self.node_index(&new_node);
+            distance = new_distance;
+        }
+        Some((node, distance))
+    }
+
+    /// Finds the knn greedily from a starting node `from`, but only returns nodes that pass the `filter`.
+    ///
+    /// Nodes which don't pass the filter are still searched through so that the graph remains connected,
+    /// but they never appear in the results. Rejected nodes are kept in the pool until `num` nodes are accepted,
+    /// without any limit, so a filter which rejects nearly everything searches the whole graph.
+    ///
+    /// The `(node, distance)` pairs are left in the `scratch` to be taken with [`Scratch::drain_neighbors`].
+    #[allow(clippy::too_many_arguments)]
+    fn search_layer_knn_filtered(
+        &self,
+        scratch: &mut Scratch<Self::Node, Unit<Self>>,
+        from: Self::Node,
+        from_distance: Unit<Self>,
+        query: &Self::K,
+        num: usize,
+        params: &SearchParams,
+        filter: impl Fn(usize) -> bool,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) {
+        scratch.begin(self.len(), num.saturating_mul(2));
+        if num == 0 {
+            return;
+        }
+        // Perform a greedy search first to save time.
+        let (from, from_distance) = self.search_layer(from, from_distance, query, visitor);
+        let from_index = self.node_index(&from);
+        let from_accepted = filter(from_index);
+        visitor.pool_insert(from_index, from_distance);
+        let entry = PoolEntry {
+            node: from.clone(),
+            distance: from_distance,
+            order: 0,
+            accepted: from_accepted,
+        };
+        scratch.pool.push(entry.clone());
+        scratch.unsearched.push(Reverse(entry));
+        // The number of entries added to the pool, which breaks ties between entries with the same distance.
+        let mut added = 1;
+        // The number of nodes in the pool which passed the filter.
+        let mut accepted = from_accepted as usize;
+
+        // Exclude the nodes in the pool to more quickly determine if a node is contained in it.
+        scratch.visit(from, || from_index);
+
+        'search: while let Some(Reverse(candidate)) = scratch.unsearched.pop() {
+            // The pool only shrinks from the worst end, and once it is full it only takes entries better than its
+            // worst one, so every entry worse than the worst entry of the pool has been evicted from it.
+            // Since this is the best unsearched entry, no unsearched entries are left in the pool.
+            if candidate > *scratch.pool.peek().unwrap() || visitor.stop() {
+                break;
+            }
+            let previous_node = candidate.node;
+            visitor.expand(self.node_index(&previous_node));
+            for (neighbor, key) in self.neighbors(&previous_node) {
+                // Make sure that we don't have a copy of this node already or we will get duplicates.
+                if scratch.is_visited(&neighbor, || self.node_index(&neighbor)) {
+                    continue;
+                }
+
+                // Compute the distance from the query.
+                let distance = self.metric().distance(query, key);
+                visitor.distance(self.node_index(&neighbor), distance);
+                // Add it if we dont have enough yet or if its better than the worst item we have.
+                if accepted < num || distance < scratch.pool.peek().unwrap().distance {
+                    let neighbor_accepted = filter(self.node_index(&neighbor));
+                    accepted += neighbor_accepted as usize;
+                    visitor.pool_insert(self.node_index(&neighbor), distance);
+                    let neighbor_index = self.node_index(&neighbor);
+                    scratch.visit(neighbor.clone(), || neighbor_index);
+                    let entry = PoolEntry {
+                        node: neighbor,
+                        distance,
+                        order: added,
+                        accepted: neighbor_accepted,
+                    };
+                    scratch.pool.push(entry.clone());
+                    scratch.unsearched.push(Reverse(entry));
+                    added += 1;
+                    // Remove the worst items until we have the right number of accepted items and the worst item is accepted.
+                    // Exclude them if exclude_all_searched is set.
+                    while accepted > num
+                        || (accepted == num && !scratch.pool.peek().unwrap().accepted)
+                    {
+                        let old = scratch.pool.pop().unwrap();
+                        accepted -= old.accepted as usize;
+                        visitor.pool_evict(self.node_index(&old.node), old.distance);
+                        if params.exclude_all_searched {
+                            scratch.unvisit(&old.node, || self.node_index(&old.node));
+                        }
+                    }
+                }
+                if visitor.stop() {
+                    break 'search;
+                }
+            }
+        }
+        visitor.pool_finish(scratch.pool.len());
+        scratch.unsearched.clear();
+        let Scratch { pool, bests, .. } = scratch;
+        bests.extend(pool.drain().filter(|entry| entry.accepted));
+        bests.sort_unstable();
+    }
+
+    /// Finds every node within `radius` of the query greedily from a starting node `from`.
+    ///
+    /// Every node within the radius is searched through, along with up to `slack` of the nearest nodes outside of it.
+    /// This allows the search to reach nodes within the radius that are only connected through nodes outside of it.
+    /// Only nodes which pass the `filter` are returned, but the rest are still searched through.
+    ///
+    /// The `(node, distance)` pairs are left in the `scratch` to be taken with [`Scratch::drain_neighbors`].
+    #[allow(clippy::too_many_arguments)]
+    fn search_layer_within_filtered(
+        &self,
+        scratch: &mut Scratch<Self::Node, Unit<Self>>,
+        from: Self::Node,
+        from_distance: Unit<Self>,
+        query: &Self::K,
+        radius: Unit<Self>,
+        slack: usize,
+        params: &SearchParams,
+        filter: impl Fn(usize) -> bool,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) {
+        scratch.begin(self.len(), slack.saturating_mul(2));
+        // Perform a greedy search first to save time.
+        let (from, from_distance) = self.search_layer(from, from_distance, query, visitor);
+        let from_index = self.node_index(&from);
+        visitor.pool_insert(from_index, from_distance);
+        let entry = PoolEntry {
+            node: from.clone(),
+            distance: from_distance,
+            order: 0,
+            accepted: from_distance <= radius && filter(from_index),
+        };
+        scratch.pool.push(entry.clone());
+        scratch.unsearched.push(Reverse(entry));
+        // The number of entries added to the pool, which breaks ties between entries with the same distance.
+        let mut added = 1;
+        // The number of nodes in the pool which are outside of the radius.
+        let mut outside = (from_distance > radius) as usize;
+
+        // Exclude the nodes in the pool to more quickly determine if a node is contained in it.
+        scratch.visit(from, || from_index);
+
+        'search: while let Some(Reverse(candidate)) = scratch.unsearched.pop() {
+            // Only nodes outside of the radius are evicted, and once one is, every node added afterwards is better
+            // than the worst entry of the pool. Like kNN search, no unsearched entries are left in the pool.
+            if candidate > *scratch.pool.peek().unwrap() || visitor.stop() {
+                break;
+            }
+            let previous_node = candidate.node;
+            visitor.expand(self.node_index(&previous_node));
+            for (neighbor, key) in self.neighbors(&previous_node) {
+                // Make sure that we don't have a copy of this node already or we will get duplicates.
+                if scratch.is_visited(&neighbor, || self.node_index(&neighbor)) {
+                    continue;
+                }
+
+                // Compute the distance from the query.
+                let distance = self.metric().distance(query, key);
+                let neighbor_index = self.node_index(&neighbor);
+                visitor.distance(neighbor_index, distance);
+                let inside = distance <= radius;
+                // Add it if it is within the radius, if we dont have enough nodes outside of the radius yet,
+                // or if its better than the worst item we have.
+                if inside || outside < slack || distance < scratch.pool.peek().unwrap().distance {
+                    outside += !inside as usize;
+                    visitor.pool_insert(neighbor_index, distance);
+                    scratch.visit(neighbor.clone(), || neighbor_index);
+                    let entry = PoolEntry {
+                        node: neighbor,
+                        distance,
+                        order: added,
+                        accepted: inside && filter(neighbor_index),
+                    };
+                    scratch.pool.push(entry.clone());
+                    scratch.unsearched.push(Reverse(entry));
+                    added += 1;
+                    // Remove the worst item if we have too many outside of the radius.
+                    // Exclude it if exclude_all_searched is set.
+                    if outside > slack {
+                        let old = scratch.pool.pop().unwrap();
+                        outside -= 1;
+                        visitor.pool_evict(self.node_index(&old.node), old.distance);
+                        if params.exclude_all_searched {
+                            scratch.unvisit(&old.node, || self.node_index(&old.node));
+                        }
+                    }
+                }
+                if visitor.stop() {
+                    break 'search;
+                }
+            }
+        }
+        visitor.pool_finish(scratch.pool.len());
+        scratch.unsearched.clear();
+        let Scratch { pool, bests, .. } = scratch;
+        bests.extend(pool.drain().filter(|entry| entry.accepted));
+        bests.sort_unstable();
+    }
+
+    /// Searches for the nearest neighbors which pass the `filter` greedily from the top layer to the bottom.
+    ///
+    /// This implementation starts with 1nn search until the bottom layer and then
+    /// performs kNN search using the memory in `scratch`.
+    ///
+    /// Returns `(node, distance)`.
+    fn search_knn_filtered(
+        &self,
+        scratch: &mut Scratch<Self::Node, Unit<Self>>,
+        query: &Self::K,
+        num: usize,
+        params: &SearchParams,
+        filter: impl Fn(usize) -> bool,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) -> Vec<(usize, Unit<Self>)> {
+        if let Some((node, distance)) = self.search_to_layer(0, query, visitor) {
+            self.search_layer_knn_filtered(
+                scratch,
+                self.layer_node(0, node),
+                distance,
+                query,
+                num + params.search_extra_knn,
+                params,
+                filter,
+                visitor,
+            );
+            scratch
+                .drain_neighbors()
+                .take(num)
+                .map(|(node, distance)| (self.node_index(&node), distance))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Searches for every node within `radius` of the query which passes the `filter`.
+    ///
+    /// This implementation starts with 1nn search until the bottom layer and then
+    /// performs a radius search using the memory in `scratch`.
+    ///
+    /// Returns `(node, distance)`.
+    #[allow(clippy::too_many_arguments)]
+    fn search_within_filtered(
+        &self,
+        scratch: &mut Scratch<Self::Node, Unit<Self>>,
+        query: &Self::K,
+        radius: Unit<Self>,
+        slack: usize,
+        params: &SearchParams,
+        filter: impl Fn(usize) -> bool,
+        visitor: &mut impl SearchVisitor<Unit<Self>>,
+    ) -> Vec<(usize, Unit<Self>)> {
+        if let Some((node, distance)) = self.search_to_layer(0, query, visitor) {
+            self.search_layer_within_filtered(
+                scratch,
+                self.layer_node(0, node),
+                distance,
+                query,
+                radius,
+                slack,
+                params,
+                filter,
+                visitor,
+            );
+            scratch
+                .drain_neighbors()
+                .map(|(node, distance)| (self.node_index(&node), distance))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Finds the nearest neighbors of a query from nearest to furthest, expanding the search as more are taken.
+///
+/// The best unyielded nodes found so far form a window of `1 + search_extra_knn` nodes. Like the pool of kNN
+/// search, every node in the window is searched through before its best node is yielded, so no node which has
+/// been found but not searched can beat the node being yielded.
+///
+/// A node may still be found only after a further node was yielded, in which case it is yielded out of order
+/// rather than dropped. Every node reachable from the root is eventually yielded.
+pub(crate) struct KnnIter<'a, G: SearchGraph, F> {
+    graph: &'a G,
+    query: &'a G::K,
+    filter: F,
+    /// The number of nodes in the window.
+    size: usize,
+    /// The best nodes found but not yet yielded, with if they were searched, keyed by distance and the order found.
+    window: BTreeMap<(Unit<G>, usize), (G::Node, bool)>,
+    /// The rest of the nodes found but not yet yielded, which move into the window as it is yielded.
+    rest: BTreeMap<(Unit<G>, usize), (G::Node, bool)>,
+    /// The keys of nodes which entered the window without being searched, with the best on top.
+    /// Keys of nodes which have since left the window are skipped.
+    unsearched: BinaryHeap<Reverse<(Unit<G>, usize)>>,
+    /// Every node found so far.
+    found: HashSet<G::Node, RandomState>,
+}
+
+impl<'a, G, F> KnnIter<'a, G, F>
+where
+    G: SearchGraph,
+    F: Fn(usize) -> bool,
+{
+    /// Begins a search for the nearest neighbors of the query which pass the `filter`.
+    pub(crate) fn new(graph: &'a G, query: &'a G::K, params: &SearchParams, filter: F) -> Self {
+        let mut iter = Self {
+            graph,
+            query,
+            filter,
+            size: 1 + params.search_extra_knn,
+            window: BTreeMap::new(),
+            rest: BTreeMap::new(),
+            unsearched: BinaryHeap::new(),
+            found: HashSet::with_hasher(RandomState::with_seeds(0, 0, 0, 0)),
+        };
+        if let Some((node, distance)) = graph.search_to_layer(1, query, &mut ()) {
+            // Perform a greedy search first to save time.
+            let (node, distance) =
+                graph.search_layer(graph.layer_node(0, node), distance, query, &mut ());
+            iter.add(node, distance);
+        }
+        iter
+    }
+
+    /// Adds a newly found node to the window if it is among the best nodes, and to the rest otherwise.
+    fn add(&mut self, node: G::Node, distance: Unit<G>) {
+        self.found.insert(node.clone());
+        let key = (distance, self.found.len());
+        if self.window.len() < self.size || key < *self.window.last_key_value().unwrap().0 {
+            self.window.insert(key, (node, false));
+            self.unsearched.push(Reverse(key));
+            if self.window.len() > self.size {
+                let (key, entry) = self.window.pop_last().unwrap();
+                self.rest.insert(key, entry);
+            }
+        } else {
+            self.rest.insert(key, (node, false));
+        }
+    }
+}
+
+impl<'a, G, F> Iterator for KnnIter<'a, G, F>
+where
+    G: SearchGraph,
+    F: Fn(usize) -> bool,
+{
+    type Item = (usize, Unit<G>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Search through every node in the window before yielding its best node.
+            while let Some(Reverse(key)) = self.unsearched.pop() {
+                let node = match self.window.get_mut(&key) {
+                    Some((node, searched)) if !*searched => {
+                        *searched = true;
+                        node.clone()
+                    }
+                    _ => continue,
+                };
+                for (neighbor, key) in self.graph.neighbors(&node) {
+                    if self.found.contains(&neighbor) {
+                        continue;
+                    }
+                    let distance = self.graph.metric().distance(self.query, key);
+                    self.add(neighbor, distance);
+                }
+            }
+
+            let ((distance, _), (node, _)) = self.window.pop_first()?;
+            // Replace the yielded node with the best of the rest.
+            if let Some((key, (rest_node, searched))) = self.rest.pop_first() {
+                if !searched {
+                    self.unsearched.push(Reverse(key));
+                }
+                self.window.insert(key, (rest_node, searched));
+            }
+
+            let index = self.graph.node_index(&node);
+            if !(self.filter)(index) {
+                continue;
+            }
+            return Some((index, distance));
+        }
+    }
+}
@@ -1,39 +1,630 @@
 extern crate std;
 
-use core::{cmp, marker::PhantomData};
+use core::{cmp, fmt, marker::PhantomData};
 
-use crate::{HVec, HggEdge, HggHeader, HggNode, NodeStorage, StrategyLite, StrategyRegular};
-use alloc::{format, vec, vec::Vec};
+use crate::{
+    HVec, Hgg, HggCore, HggEdge, HggHeader, HggInternal, HggLite, HggNode, NodeIndex, NodeStorage,
+    StrategyLite, StrategyRegular, VisitedSet,
+};
+use alloc::{format, string::String, vec, vec::Vec};
 use header_vec::HeaderVec;
 use serde::{
-    de::{self, Unexpected},
-    Deserialize, Deserializer, Serialize,
+    de::{self, MapAccess, SeqAccess, Unexpected, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
+use space::Metric;
+
+/// The magic bytes at the start of every serialized HGG.
+const MAGIC: [u8; 4] = *b"HGG\0";
+
+/// The version of the serialized layout.
+///
+/// Increment this whenever the layout of [`HggCore`] changes, and migrate the older layouts after
+/// [`ContainerHeader::format`] accepts them so that existing indices keep loading.
+const FORMAT_VERSION: u32 = 1;
+
+/// Explains what to do when the container is not found.
+const LEGACY_HINT: &str = "indices serialized before the format was versioned must be loaded with \
+                           `LegacyHgg` or `LegacyHggLite`";
+
+/// The fields of the container, in the order they are serialized.
+const FIELDS: &[&str] = &[
+    "magic",
+    "format",
+    "crate_version",
+    "strategy",
+    "nodes",
+    "checksum",
+    "hgg",
+];
+
+/// The strategy of the serialized HGG, so that an [`HggLite`] isn't loaded as an [`Hgg`] or vice versa.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Strategy {
+    Regular,
+    Lite,
+}
+
+/// Identifies the strategy of an [`HggCore`] when it is serialized.
+trait SerializedStrategy {
+    const STRATEGY: Strategy;
+}
+
+impl SerializedStrategy for StrategyRegular {
+    const STRATEGY: Strategy = Strategy::Regular;
+}
+
+impl SerializedStrategy for StrategyLite {
+    const STRATEGY: Strategy = Strategy::Lite;
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum Field {
+    Magic,
+    Format,
+    CrateVersion,
+    Strategy,
+    Nodes,
+    Checksum,
+    Hgg,
+}
 
 #[derive(Debug, Serialize)]
-struct HggNodeSerialize<'a, K, V> {
+struct HggNodeSerialize<'a, K, V, Ix> {
     key: &'a K,
     value: &'a V,
     /// Contains the edges of each layer of the graph on which this exists.
-    layers: Vec<Vec<usize>>,
+    layers: Vec<Vec<Ix>>,
     /// Forms a linked list through the nodes that creates the freshening order.
-    next: usize,
+    next: Ix,
+    /// Marks the node as deleted, so it is only used to route searches.
+    deleted: bool,
 }
 
 #[derive(Debug, Deserialize)]
-struct HggNodeDeserialize<K, V> {
+struct HggNodeDeserialize<K, V, Ix> {
     key: K,
     value: V,
     /// Contains the edges of each layer of the graph on which this exists.
-    layers: Vec<Vec<usize>>,
+    layers: Vec<Vec<Ix>>,
     /// Forms a linked list through the nodes that creates the freshening order.
+    next: Ix,
+    /// Marks the node as deleted, so it is only used to route searches.
+    deleted: bool,
+}
+
+/// Builds the nodes of the graph from their deserialized form, reconnecting the edges by index.
+trait FromDeserializedNodes<K, V, Ix>: Sized {
+    fn from_deserialized_nodes<E>(nodes: Vec<HggNodeDeserialize<K, V, Ix>>) -> Result<Self, E>
+    where
+        E: de::Error;
+}
+
+/// Checks that every deserialized node can be indexed by `Ix`, since creating an index that is too large panics.
+fn check_node_capacity<Ix, E>(nodes: usize) -> Result<(), E>
+where
+    Ix: NodeIndex,
+    E: de::Error,
+{
+    if nodes > 0 && nodes - 1 > <Ix as NodeIndex>::max() {
+        return Err(E::custom(format!(
+            "serialized HGG has {} nodes, but the node index type can only index up to node {}",
+            nodes,
+            <Ix as NodeIndex>::max()
+        )));
+    }
+    Ok(())
+}
+
+impl<'de, K, V, HK, S, Ix> Deserialize<'de> for NodeStorage<K, V, HK, S, Ix>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    Ix: Deserialize<'de>,
+    Self: FromDeserializedNodes<K, V, Ix>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::from_deserialized_nodes(Vec::<HggNodeDeserialize<K, V, Ix>>::deserialize(
+            deserializer,
+        )?)
+    }
+}
+
+/// Computes the FNV-1a hash of the structure of the graph.
+///
+/// This is stable across platforms and versions, unlike the hashers used in memory.
+/// Keys and values are not included, since they are not required to be hashable.
+/// Settings added after the format was versioned are not included either, since they take their defaults
+/// when they are missing, which would change the checksum of files written before they existed.
+struct Checksum(u64);
+
+impl Checksum {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, n: usize) {
+        for byte in (n as u64).to_le_bytes() {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl<M, K, V, HK, S, Ix> HggCore<M, K, V, HK, S, Ix>
+where
+    Ix: NodeIndex,
+{
+    /// Computes a checksum of the structure of the graph, its counts, and the settings it was versioned with.
+    fn checksum(&self) -> u64 {
+        let mut checksum = Checksum::new();
+        checksum.write(self.nodes.len());
+        for node in self.nodes.iter() {
+            checksum.write(node.layers.len());
+            for layer in &node.layers {
+                checksum.write(layer.len());
+                for edge in layer.as_slice() {
+                    checksum.write(edge.neighbor.node.index());
+                }
+            }
+            checksum.write(node.next.index());
+            checksum.write(node.deleted as usize);
+        }
+        for &n in [self.root.index(), self.freshest.index(), self.deleted].iter() {
+            checksum.write(n);
+        }
+        for counts in [&self.edges, &self.node_counts] {
+            checksum.write(counts.len());
+            for &n in counts {
+                checksum.write(n);
+            }
+        }
+        for &n in [
+            self.search_extra_knn,
+            self.freshens,
+            self.exclude_all_searched as usize,
+            self.insert_knn,
+        ]
+        .iter()
+        {
+            checksum.write(n);
+        }
+        checksum.0
+    }
+}
+
+/// Serializes the graph inside of a container with a header that identifies it.
+fn serialize_container<M, K, V, HK, S, Ix, Ser>(
+    name: &'static str,
+    hgg: &HggCore<M, K, V, HK, S, Ix>,
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error>
+where
+    HggCore<M, K, V, HK, S, Ix>: Serialize,
+    S: SerializedStrategy,
+    Ix: NodeIndex,
+    Ser: Serializer,
+{
+    let mut state = serializer.serialize_struct(name, FIELDS.len())?;
+    state.serialize_field("magic", &MAGIC)?;
+    state.serialize_field("format", &FORMAT_VERSION)?;
+    state.serialize_field("crate_version", env!("CARGO_PKG_VERSION"))?;
+    state.serialize_field("strategy", &S::STRATEGY)?;
+    state.serialize_field("nodes", &hgg.nodes.len())?;
+    state.serialize_field("checksum", &hgg.checksum())?;
+    state.serialize_field("hgg", hgg)?;
+    state.end()
+}
+
+/// Deserializes a graph serialized by [`serialize_container`], checking the header and the integrity of the graph.
+#[allow(clippy::type_complexity)]
+fn deserialize_container<'de, M, K, V, HK, S, Ix, D>(
+    name: &'static str,
+    deserializer: D,
+) -> Result<HggCore<M, K, V, HK, S, Ix>, D::Error>
+where
+    HggCore<M, K, V, HK, S, Ix>: Deserialize<'de>,
+    S: SerializedStrategy,
+    Ix: NodeIndex,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_struct(
+        name,
+        FIELDS,
+        ContainerVisitor {
+            name,
+            _phantom: PhantomData,
+        },
+    )
+}
+
+/// The header of the container, which is checked as soon as each field is read.
+#[derive(Default)]
+struct ContainerHeader {
+    magic: bool,
+    format: Option<u32>,
+    crate_version: Option<String>,
+    strategy: bool,
+    nodes: Option<usize>,
+    checksum: Option<u64>,
+}
+
+impl ContainerHeader {
+    fn magic<E: de::Error>(&mut self, magic: [u8; 4]) -> Result<(), E> {
+        if magic != MAGIC {
+            return Err(E::custom(format!("bad magic bytes; {}", LEGACY_HINT)));
+        }
+        self.magic = true;
+        Ok(())
+    }
+
+    fn format<E: de::Error>(&mut self, format: u32) -> Result<(), E> {
+        // There are no older versions of the container to migrate yet.
+        if format != FORMAT_VERSION {
+            return Err(E::custom(format!(
+                "serialized HGG has format version {}, but only version {} is supported",
+                format, FORMAT_VERSION
+            )));
+        }
+        self.format = Some(format);
+        Ok(())
+    }
+
+    fn strategy<E: de::Error>(&mut self, strategy: Strategy, expected: Strategy) -> Result<(), E> {
+        if strategy != expected {
+            return Err(E::custom(format!(
+                "serialized HGG uses the {:?} strategy, but the {:?} strategy was expected",
+                strategy, expected
+            )));
+        }
+        self.strategy = true;
+        Ok(())
+    }
+
+    /// Checks that every field of the header was read.
+    fn complete<E: de::Error>(&self) -> Result<(u64, usize), E> {
+        if !self.magic {
+            return Err(E::custom(format!("missing field `magic`; {}", LEGACY_HINT)));
+        }
+        if self.format.is_none() {
+            return Err(E::missing_field("format"));
+        }
+        if self.crate_version.is_none() {
+            return Err(E::missing_field("crate_version"));
+        }
+        if !self.strategy {
+            return Err(E::missing_field("strategy"));
+        }
+        let nodes = self.nodes.ok_or_else(|| E::missing_field("nodes"))?;
+        let checksum = self.checksum.ok_or_else(|| E::missing_field("checksum"))?;
+        Ok((checksum, nodes))
+    }
+
+    /// Checks that the graph matches the header and that it is intact.
+    fn check<M, K, V, HK, S, Ix, E>(&self, hgg: &HggCore<M, K, V, HK, S, Ix>) -> Result<(), E>
+    where
+        Ix: NodeIndex,
+        E: de::Error,
+    {
+        let (checksum, nodes) = self.complete()?;
+        if hgg.nodes.len() != nodes {
+            return Err(E::custom(format!(
+                "serialized HGG should have {} nodes, but it has {}",
+                nodes,
+                hgg.nodes.len()
+            )));
+        }
+        if hgg.checksum() != checksum {
+            return Err(E::custom(format!(
+                "serialized HGG has a bad checksum (written by hgg {})",
+                self.crate_version.as_deref().unwrap_or_default()
+            )));
+        }
+        // The cached keys were just created from the node keys, so they don't need to be checked.
+        hgg.validate(|_, _| true)
+            .map_err(|e| E::custom(format!("serialized HGG is corrupt: {}", e)))
+    }
+}
+
+struct ContainerVisitor<M, K, V, HK, S, Ix> {
+    name: &'static str,
+    _phantom: PhantomData<HggCore<M, K, V, HK, S, Ix>>,
+}
+
+impl<'de, M, K, V, HK, S, Ix> Visitor<'de> for ContainerVisitor<M, K, V, HK, S, Ix>
+where
+    HggCore<M, K, V, HK, S, Ix>: Deserialize<'de>,
+    S: SerializedStrategy,
+    Ix: NodeIndex,
+{
+    type Value = HggCore<M, K, V, HK, S, Ix>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a serialized {}", self.name)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut header = ContainerHeader::default();
+        let missing = |index| de::Error::invalid_length(index, &self);
+        header.magic(seq.next_element()?.ok_or_else(|| missing(0))?)?;
+        header.format(seq.next_element()?.ok_or_else(|| missing(1))?)?;
+        header.crate_version = Some(seq.next_element()?.ok_or_else(|| missing(2))?);
+        header.strategy(seq.next_element()?.ok_or_else(|| missing(3))?, S::STRATEGY)?;
+        header.nodes = Some(seq.next_element()?.ok_or_else(|| missing(4))?);
+        header.checksum = Some(seq.next_element()?.ok_or_else(|| missing(5))?);
+        let hgg = seq.next_element()?.ok_or_else(|| missing(6))?;
+        header.check(&hgg)?;
+        Ok(hgg)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut header = ContainerHeader::default();
+        let mut hgg = None;
+        while let Some(field) = map.next_key()? {
+            match field {
+                Field::Magic => header.magic(map.next_value()?)?,
+                Field::Format => header.format(map.next_value()?)?,
+                Field::CrateVersion => header.crate_version = Some(map.next_value()?),
+                Field::Strategy => header.strategy(map.next_value()?, S::STRATEGY)?,
+                Field::Nodes => header.nodes = Some(map.next_value()?),
+                Field::Checksum => header.checksum = Some(map.next_value()?),
+                Field::Hgg => hgg = Some(map.next_value()?),
+            }
+        }
+        let hgg = hgg.ok_or_else(|| de::Error::missing_field("hgg"))?;
+        header.check(&hgg)?;
+        Ok(hgg)
+    }
+}
+
+impl<M, K, V, Ix> Serialize for Hgg<M, K, V, Ix>
+where
+    M: Serialize,
+    K: Serialize,
+    V: Serialize,
+    Ix: NodeIndex + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_container("Hgg", &self.hgg, serializer)
+    }
+}
+
+impl<'de, M, K, V, Ix> Deserialize<'de> for Hgg<M, K, V, Ix>
+where
+    M: Deserialize<'de>,
+    K: Deserialize<'de> + Clone,
+    V: Deserialize<'de>,
+    Ix: NodeIndex + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_container("Hgg", deserializer).map(|hgg| Self { hgg })
+    }
+}
+
+impl<M, K, V, Ix> Serialize for HggLite<M, K, V, Ix>
+where
+    M: Serialize,
+    K: Serialize,
+    V: Serialize,
+    Ix: NodeIndex + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_container("HggLite", &self.hgg, serializer)
+    }
+}
+
+impl<'de, M, K, V, Ix> Deserialize<'de> for HggLite<M, K, V, Ix>
+where
+    M: Deserialize<'de>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    Ix: NodeIndex + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_container("HggLite", deserializer).map(|hgg| Self { hgg })
+    }
+}
+
+/// The layout of a node before `deleted` was added.
+#[derive(Deserialize)]
+struct LegacyHggNode<K, V> {
+    key: K,
+    value: V,
+    layers: Vec<Vec<usize>>,
     next: usize,
 }
 
-impl<K, V> Serialize for NodeStorage<K, V, K, StrategyRegular>
+/// The nodes in the layout before `deleted` was added.
+struct LegacyNodeStorage<K, V, HK, S>(NodeStorage<K, V, HK, S, usize>);
+
+impl<'de, K, V, HK, S> Deserialize<'de> for LegacyNodeStorage<K, V, HK, S>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    NodeStorage<K, V, HK, S, usize>: FromDeserializedNodes<K, V, usize>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let nodes = Vec::<LegacyHggNode<K, V>>::deserialize(deserializer)?
+            .into_iter()
+            .map(
+                |LegacyHggNode {
+                     key,
+                     value,
+                     layers,
+                     next,
+                 }| HggNodeDeserialize {
+                    key,
+                    value,
+                    layers,
+                    next,
+                    deleted: false,
+                },
+            )
+            .collect();
+        NodeStorage::from_deserialized_nodes(nodes).map(Self)
+    }
+}
+
+/// The layout of [`HggCore`] before the container was versioned.
+#[derive(Deserialize)]
+#[serde(bound(
+    deserialize = "M: Deserialize<'de>, K: Deserialize<'de>, V: Deserialize<'de>, NodeStorage<K, V, HK, S, usize>: FromDeserializedNodes<K, V, usize>"
+))]
+struct LegacyHggCore<M, K, V, HK, S> {
+    metric: M,
+    nodes: LegacyNodeStorage<K, V, HK, S>,
+    root: usize,
+    freshest: usize,
+    edges: Vec<usize>,
+    node_counts: Vec<usize>,
+    search_extra_knn: usize,
+    freshens: usize,
+    exclude_all_searched: bool,
+    insert_knn: usize,
+    #[serde(rename = "_phantom")]
+    _phantom: PhantomData<S>,
+}
+
+impl<M, K, V, HK, S> LegacyHggCore<M, K, V, HK, S>
+where
+    M: Metric<K>,
+    HggCore<M, K, V, HK, S, usize>: HggInternal<M = M, K = K, V = V, HK = HK, Ix = usize>,
+{
+    /// Migrates the graph to the current layout, checking its integrity.
+    fn migrate<E>(self) -> Result<HggCore<M, K, V, HK, S, usize>, E>
+    where
+        E: de::Error,
+    {
+        let hgg = HggCore {
+            metric: self.metric,
+            nodes: self.nodes.0,
+            root: self.root,
+            freshest: self.freshest,
+            edges: self.edges,
+            node_counts: self.node_counts,
+            deleted: 0,
+            search_extra_knn: self.search_extra_knn,
+            freshens: self.freshens,
+            exclude_all_searched: self.exclude_all_searched,
+            visited: VisitedSet::default(),
+            insert_knn: self.insert_knn,
+            repair_unreachable: false,
+            _phantom: PhantomData,
+        };
+        // The cached keys were just created from the node keys, so they don't need to be checked.
+        hgg.validate(|_, _| true)
+            .map_err(|e| E::custom(format!("serialized HGG is corrupt: {}", e)))?;
+        Ok(hgg)
+    }
+}
+
+/// An [`Hgg`] serialized by a version of this crate from before the serialized format was versioned.
+///
+/// Deserialize this instead of [`Hgg`] to load such an index, and then convert it into an [`Hgg`]
+/// with [`From`]. Serializing the [`Hgg`] afterwards writes the current format.
+#[derive(Debug)]
+pub struct LegacyHgg<M, K, V>(Hgg<M, K, V>);
+
+impl<'de, M, K, V> Deserialize<'de> for LegacyHgg<M, K, V>
+where
+    M: Metric<K> + Deserialize<'de>,
+    K: Deserialize<'de> + Clone,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(
+            rename = "Hgg",
+            bound(
+                deserialize = "M: Deserialize<'de>, K: Deserialize<'de> + Clone, V: Deserialize<'de>"
+            )
+        )]
+        struct Legacy<M, K, V> {
+            hgg: LegacyHggCore<M, K, V, K, StrategyRegular>,
+        }
+
+        let legacy = Legacy::deserialize(deserializer)?;
+        legacy.hgg.migrate().map(|hgg| Self(Hgg { hgg }))
+    }
+}
+
+impl<M, K, V> From<LegacyHgg<M, K, V>> for Hgg<M, K, V> {
+    fn from(legacy: LegacyHgg<M, K, V>) -> Self {
+        legacy.0
+    }
+}
+
+/// An [`HggLite`] serialized by a version of this crate from before the serialized format was versioned.
+///
+/// Deserialize this instead of [`HggLite`] to load such an index, and then convert it into an [`HggLite`]
+/// with [`From`]. Serializing the [`HggLite`] afterwards writes the current format.
+#[derive(Debug)]
+pub struct LegacyHggLite<M, K, V>(HggLite<M, K, V>);
+
+impl<'de, M, K, V> Deserialize<'de> for LegacyHggLite<M, K, V>
+where
+    M: Metric<K> + Deserialize<'de>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(
+            rename = "HggLite",
+            bound(deserialize = "M: Deserialize<'de>, K: Deserialize<'de>, V: Deserialize<'de>")
+        )]
+        struct Legacy<M, K, V> {
+            hgg: LegacyHggCore<M, K, V, (), StrategyLite>,
+        }
+
+        let legacy = Legacy::deserialize(deserializer)?;
+        legacy.hgg.migrate().map(|hgg| Self(HggLite { hgg }))
+    }
+}
+
+impl<M, K, V> From<LegacyHggLite<M, K, V>> for HggLite<M, K, V> {
+    fn from(legacy: LegacyHggLite<M, K, V>) -> Self {
+        legacy.0
+    }
+}
+
+impl<K, V, Ix> Serialize for NodeStorage<K, V, K, StrategyRegular, Ix>
 where
     K: Serialize,
     V: Serialize,
+    Ix: NodeIndex + Serialize,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -55,21 +646,22 @@ where
                     })
                     .collect(),
                 next: e.next,
+                deleted: e.deleted,
             }
         }))
     }
 }
 
-impl<'de, K, V> Deserialize<'de> for NodeStorage<K, V, K, StrategyRegular>
+impl<K, V, Ix> FromDeserializedNodes<K, V, Ix> for NodeStorage<K, V, K, StrategyRegular, Ix>
 where
-    K: Deserialize<'de> + Clone,
-    V: Deserialize<'de>,
+    K: Clone,
+    Ix: NodeIndex,
 {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn from_deserialized_nodes<E>(nodesd: Vec<HggNodeDeserialize<K, V, Ix>>) -> Result<Self, E>
     where
-        D: Deserializer<'de>,
+        E: de::Error,
     {
-        let nodesd = Vec::<HggNodeDeserialize<K, V>>::deserialize(deserializer)?;
+        check_node_capacity::<Ix, E>(nodesd.len())?;
 
         // Create all the HggNode, but do not populate the edges yet.
         // Save the layers.
@@ -82,6 +674,7 @@ where
                 value,
                 layers,
                 next,
+                deleted,
             },
         ) in nodesd.into_iter().enumerate()
         {
@@ -92,7 +685,7 @@ where
                         cmp::max(layer.len(), 1),
                         HggHeader {
                             key: key.clone(),
-                            node,
+                            node: Ix::new(node),
                         },
                     )
                 })
@@ -102,11 +695,12 @@ where
                 value,
                 layers: empty_layers,
                 next,
+                deleted,
             });
             nodes_layers.push(layers);
         }
 
-        let node_data: Vec<(K, Vec<HVec<K>>)> = nodes
+        let node_data: Vec<(K, Vec<HVec<K, Ix>>)> = nodes
             .iter()
             .map(|node| {
                 (
@@ -124,19 +718,20 @@ where
             for (layer, neighbors) in layers.into_iter().enumerate() {
                 for neighbor in neighbors {
                     // Get the key and weak refs for the target node.
-                    let (key, neighbor_layers) = node_data.get(neighbor).ok_or_else(|| {
-                        // If the index is pointing to an out-of-bounds neighbor, handle that.
-                        de::Error::invalid_value(
-                            Unexpected::Unsigned(neighbor as u64),
-                            &format!("one of {} valid nodes", node_data.len()).as_str(),
-                        )
-                    })?;
+                    let (key, neighbor_layers) =
+                        node_data.get(neighbor.index()).ok_or_else(|| {
+                            // If the index is pointing to an out-of-bounds neighbor, handle that.
+                            E::invalid_value(
+                                Unexpected::Unsigned(neighbor.index() as u64),
+                                &format!("one of {} valid nodes", node_data.len()).as_str(),
+                            )
+                        })?;
                     // Get the weak ref for the target node.
                     let neighbor = neighbor_layers
                         .get(layer)
                         .ok_or_else(|| {
                             // If it didn't have the expected layer, there is an error.
-                            de::Error::invalid_length(
+                            E::invalid_length(
                                 layer,
                                 &format!("one of target node's {} layers", neighbor_layers.len())
                                     .as_str(),
@@ -156,10 +751,11 @@ where
     }
 }
 
-impl<K, V> Serialize for NodeStorage<K, V, (), StrategyLite>
+impl<K, V, Ix> Serialize for NodeStorage<K, V, (), StrategyLite, Ix>
 where
     K: Serialize,
     V: Serialize,
+    Ix: NodeIndex + Serialize,
 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -181,21 +777,21 @@ where
                     })
                     .collect(),
                 next: e.next,
+                deleted: e.deleted,
             }
         }))
     }
 }
 
-impl<'de, K, V> Deserialize<'de> for NodeStorage<K, V, (), StrategyLite>
+impl<K, V, Ix> FromDeserializedNodes<K, V, Ix> for NodeStorage<K, V, (), StrategyLite, Ix>
 where
-    K: Deserialize<'de>,
-    V: Deserialize<'de>,
+    Ix: NodeIndex,
 {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn from_deserialized_nodes<E>(nodesd: Vec<HggNodeDeserialize<K, V, Ix>>) -> Result<Self, E>
     where
-        D: Deserializer<'de>,
+        E: de::Error,
     {
-        let nodesd = Vec::<HggNodeDeserialize<K, V>>::deserialize(deserializer)?;
+        check_node_capacity::<Ix, E>(nodesd.len())?;
 
         // Create all the HggNode, but do not populate the edges yet.
         // Save the layers.
@@ -208,13 +804,20 @@ where
                 value,
                 layers,
                 next,
+                deleted,
             },
         ) in nodesd.into_iter().enumerate()
         {
             let empty_layers = layers
                 .iter()
                 .map(|layer| {
-                    HeaderVec::with_capacity(cmp::max(layer.len(), 1), HggHeader { key: (), node })
+                    HeaderVec::with_capacity(
+                        cmp::max(layer.len(), 1),
+                        HggHeader {
+                            key: (),
+                            node: Ix::new(node),
+                        },
+                    )
                 })
                 .collect();
             nodes.push(HggNode {
@@ -222,11 +825,12 @@ where
                 value,
                 layers: empty_layers,
                 next,
+                deleted,
             });
             nodes_layers.push(layers);
         }
 
-        let node_data: Vec<Vec<HVec<()>>> = nodes
+        let node_data: Vec<Vec<HVec<(), Ix>>> = nodes
             .iter()
             .map(|node| {
                 node.layers
@@ -241,10 +845,10 @@ where
             for (layer, neighbors) in layers.into_iter().enumerate() {
                 for neighbor in neighbors {
                     // Get the key and weak refs for the target node.
-                    let neighbor_layers = node_data.get(neighbor).ok_or_else(|| {
+                    let neighbor_layers = node_data.get(neighbor.index()).ok_or_else(|| {
                         // If the index is pointing to an out-of-bounds neighbor, handle that.
-                        de::Error::invalid_value(
-                            Unexpected::Unsigned(neighbor as u64),
+                        E::invalid_value(
+                            Unexpected::Unsigned(neighbor.index() as u64),
                             &format!("one of {} valid nodes", node_data.len()).as_str(),
                         )
                     })?;
@@ -253,7 +857,7 @@ where
                         .get(layer)
                         .ok_or_else(|| {
                             // If it didn't have the expected layer, there is an error.
-                            de::Error::invalid_length(
+                            E::invalid_length(
                                 layer,
                                 &format!("one of target node's {} layers", neighbor_layers.len())
                                     .as_str(),
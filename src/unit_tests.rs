@@ -1,6 +1,6 @@
 extern crate std;
 
-use crate::Hgg;
+use crate::{Hgg, HggInvariantError};
 use alloc::vec::Vec;
 use bitarray::{BitArray, Hamming};
 use rand::{Rng, SeedableRng};
@@ -41,3 +41,66 @@ fn random_insertion_stats() {
         assert_eq!(distance, 0);
     }
 }
+
+#[test]
+fn validate_detects_corruption() {
+    let mut hgg: Hgg<Hamming, BitArray<32>, ()> = Hgg::default().insert_knn(100);
+
+    // Use a PRNG with good statistical properties for generating 64-bit numbers.
+    let mut rng = rand_xoshiro::Xoshiro256PlusPlus::seed_from_u64(0);
+
+    // Insert random keys into HGG.
+    for key in (&mut rng)
+        .sample_iter::<[u8; 32], _>(rand::distributions::Standard)
+        .map(BitArray::new)
+        .take(1 << 8)
+    {
+        hgg.insert(key, ());
+    }
+    assert_eq!(hgg.validate(), Ok(()));
+
+    // Break each of the counts in turn.
+    hgg.hgg.edges[0] += 1;
+    assert!(matches!(
+        hgg.validate(),
+        Err(HggInvariantError::EdgeCountMismatch { layer: 0, .. })
+    ));
+    hgg.hgg.edges[0] -= 1;
+    hgg.hgg.node_counts[1] -= 1;
+    assert!(matches!(
+        hgg.validate(),
+        Err(HggInvariantError::NodeCountMismatch { layer: 1, .. })
+    ));
+    hgg.hgg.node_counts[1] += 1;
+    hgg.hgg.deleted = 1;
+    assert_eq!(
+        hgg.validate(),
+        Err(HggInvariantError::DeletedCountMismatch {
+            recorded: 1,
+            actual: 0
+        })
+    );
+    hgg.hgg.deleted = 0;
+
+    // Make the freshening order skip the stalest node.
+    let freshest = hgg.hgg.freshest;
+    let stalest = hgg.hgg.nodes[freshest].next;
+    hgg.hgg.nodes[freshest].next = hgg.hgg.nodes[stalest].next;
+    assert!(matches!(
+        hgg.validate(),
+        Err(HggInvariantError::BrokenFresheningOrder { .. })
+    ));
+    hgg.hgg.nodes[freshest].next = stalest;
+
+    // Remove one side of an edge.
+    let neighbor = hgg.hgg.nodes[0].layers[0].as_slice()[0].neighbor.node;
+    hgg.hgg.nodes[0].layers[0].retain(|edge| edge.neighbor.node != neighbor);
+    assert_eq!(
+        hgg.validate(),
+        Err(HggInvariantError::AsymmetricEdge {
+            node: neighbor,
+            neighbor: 0,
+            layer: 0
+        })
+    );
+}
@@ -0,0 +1,551 @@
+use crate::{
+    search::{Scratch, SearchGraph},
+    Hgg, HggCore, HggInternal, HggLite, NodeIndex, SearchParams,
+};
+use alloc::vec::Vec;
+use core::{
+    convert::TryFrom,
+    fmt::{self, Display},
+    mem,
+};
+use space::{Knn, KnnPoints, Metric, Neighbor};
+use zerocopy::{
+    little_endian::{U32, U64},
+    FromBytes, Immutable, IntoBytes,
+};
+
+/// The magic bytes at the start of every view.
+const MAGIC: [u8; 8] = *b"HGGVIEW\0";
+
+/// The version of the view layout.
+const VIEW_VERSION: u32 = 1;
+
+/// The length of the header in bytes.
+///
+/// The header contains, in order and in little endian:
+///
+/// * `magic: [u8; 8]`
+/// * `version: u32`
+/// * `key_size: u32`
+/// * `key_align: u32`
+/// * `exclude_all_searched: u32`
+/// * `nodes: u64`
+/// * `root: u64`
+/// * `search_extra_knn: u64`
+/// * `node_layers: u64`, the sum of the number of layers of every node
+/// * `edges: u64`, the sum of the number of neighbors of every node on every layer
+const HEADER_LEN: usize = 64;
+
+/// The errors that can occur when creating an [`HggView`] from bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HggViewError {
+    /// The bytes don't start with the magic bytes of a view.
+    BadMagic,
+    /// The view was written with a layout version this crate doesn't understand.
+    UnsupportedVersion { version: u32 },
+    /// The size or alignment of the keys in the view doesn't match the key type.
+    KeyLayoutMismatch { size: u32, align: u32 },
+    /// The bytes end before the end of the view.
+    Truncated { len: usize, expected: usize },
+    /// The value in the header at `offset` doesn't fit in a `usize` on this platform.
+    Overflow { offset: usize },
+    /// The keys aren't aligned in memory. The bytes must be aligned to the alignment of the key type.
+    Misaligned,
+    /// The root isn't a node of the view, or isn't the only node on the top layer.
+    InvalidRoot { root: usize },
+    /// The layers of a node don't fit in the view.
+    InvalidLayers { node: usize },
+    /// The edges of a node on a layer don't fit in the view or refer to a node not on that layer.
+    InvalidEdges { node: usize, layer: usize },
+}
+
+impl Display for HggViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::BadMagic => write!(f, "bytes do not start with the magic bytes of a view"),
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported view version {}", version)
+            }
+            Self::KeyLayoutMismatch { size, align } => write!(
+                f,
+                "view contains keys with size {} and alignment {}, which does not match the key type",
+                size, align
+            ),
+            Self::Truncated { len, expected } => write!(
+                f,
+                "view is {} bytes long, but it must be at least {} bytes long",
+                len, expected
+            ),
+            Self::Overflow { offset } => write!(
+                f,
+                "header value at offset {} does not fit in a usize on this platform",
+                offset
+            ),
+            Self::Misaligned => write!(f, "keys of the view are not aligned in memory"),
+            Self::InvalidRoot { root } => write!(f, "root {} is not alone on the top layer", root),
+            Self::InvalidLayers { node } => write!(f, "node {} has invalid layers", node),
+            Self::InvalidEdges { node, layer } => {
+                write!(f, "node {} has invalid edges on layer {}", node, layer)
+            }
+        }
+    }
+}
+
+/// Splits the next `len` items of type `T` off of the front of `bytes` starting at `offset`.
+fn take<'a, T>(bytes: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [T], HggViewError>
+where
+    T: FromBytes + Immutable,
+{
+    let expected = len
+        .checked_mul(mem::size_of::<T>())
+        .and_then(|size| size.checked_add(*offset))
+        .ok_or(HggViewError::Truncated {
+            len: bytes.len(),
+            expected: usize::MAX,
+        })?;
+    let slice = bytes
+        .get(*offset..expected)
+        .ok_or(HggViewError::Truncated {
+            len: bytes.len(),
+            expected,
+        })?;
+    *offset = expected;
+    <[T]>::ref_from_bytes(slice).map_err(|_| HggViewError::Misaligned)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut array = [0; 4];
+    array.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(array)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<usize, HggViewError> {
+    let mut array = [0; 8];
+    array.copy_from_slice(&bytes[offset..offset + 8]);
+    usize::try_from(u64::from_le_bytes(array)).map_err(|_| HggViewError::Overflow { offset })
+}
+
+/// Returns the offset of the keys, which are aligned to the alignment of `K` relative to the start of the view.
+fn keys_offset<K>() -> usize {
+    let align = mem::align_of::<K>();
+    HEADER_LEN.div_ceil(align) * align
+}
+
+/// Writes the layers and edges of the graph in the flat layout read by [`HggView`].
+///
+/// Panics if the graph has more than [`u32::MAX`] nodes.
+fn write_view<M, K, V, HK, Strategy, Ix>(hgg: &HggCore<M, K, V, HK, Strategy, Ix>) -> Vec<u8>
+where
+    M: Metric<K>,
+    K: IntoBytes + Immutable,
+    Ix: NodeIndex,
+    HggCore<M, K, V, HK, Strategy, Ix>: HggInternal<M = M, K = K, V = V, HK = HK, Ix = Ix>,
+{
+    assert!(
+        hgg.len() <= u32::MAX as usize,
+        "views can contain at most u32::MAX nodes"
+    );
+    let node_layers: usize = hgg.nodes.iter().map(|node| node.layers()).sum();
+    let edges: usize = hgg
+        .nodes
+        .iter()
+        .flat_map(|node| node.layers.iter().map(|layer| layer.len()))
+        .sum();
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    for value in [
+        VIEW_VERSION,
+        mem::size_of::<K>() as u32,
+        mem::align_of::<K>() as u32,
+        hgg.exclude_all_searched as u32,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in [
+        hgg.len(),
+        hgg.root.index(),
+        hgg.search_extra_knn,
+        node_layers,
+        edges,
+    ] {
+        bytes.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+    bytes.resize(keys_offset::<K>(), 0);
+
+    for node in &*hgg.nodes {
+        bytes.extend_from_slice(node.key.as_bytes());
+    }
+    let mut layer_index = 0;
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    for node in &*hgg.nodes {
+        layer_index += node.layers() as u64;
+        bytes.extend_from_slice(&layer_index.to_le_bytes());
+    }
+    let mut edge_offset = 0;
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    for layer in hgg.nodes.iter().flat_map(|node| &node.layers) {
+        edge_offset += layer.len() as u64;
+        bytes.extend_from_slice(&edge_offset.to_le_bytes());
+    }
+    for layer in hgg.nodes.iter().flat_map(|node| &node.layers) {
+        for edge in layer.as_slice() {
+            bytes.extend_from_slice(&(edge.neighbor.node.index() as u32).to_le_bytes());
+        }
+    }
+    bytes.extend(hgg.nodes.iter().map(|node| node.deleted as u8));
+    bytes
+}
+
+impl<M, K, V, Ix> Hgg<M, K, V, Ix>
+where
+    M: Metric<K>,
+    K: Clone + IntoBytes + Immutable,
+    Ix: NodeIndex,
+{
+    /// Writes the graph in the flat layout that is searched by [`HggView`] without deserializing it.
+    ///
+    /// The values are not written, so store them separately if they are needed.
+    ///
+    /// Panics if the graph has more than [`u32::MAX`] nodes.
+    pub fn to_view_bytes(&self) -> Vec<u8> {
+        write_view(&self.hgg)
+    }
+}
+
+impl<M, K, V, Ix> HggLite<M, K, V, Ix>
+where
+    M: Metric<K>,
+    K: IntoBytes + Immutable,
+    Ix: NodeIndex,
+{
+    /// Writes the graph in the flat layout that is searched by [`HggView`] without deserializing it.
+    ///
+    /// The values are not written, so store them separately if they are needed.
+    ///
+    /// Panics if the graph has more than [`u32::MAX`] nodes.
+    pub fn to_view_bytes(&self) -> Vec<u8> {
+        write_view(&self.hgg)
+    }
+}
+
+/// A node of an [`HggView`] on a specific layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ViewNode {
+    node: usize,
+    layer: usize,
+}
+
+/// A read-only HGG which is searched directly from bytes without deserializing it.
+///
+/// The bytes are produced by [`HggLite::to_view_bytes`](crate::HggLite::to_view_bytes) or
+/// [`Hgg::to_view_bytes`](crate::Hgg::to_view_bytes). They are typically written to a file which is
+/// memory-mapped, so that a large index can be searched without loading all of it into memory.
+/// The values of the graph are not part of the view, so store them separately and look them up by node index.
+///
+/// The keys are stored in their in-memory representation, so the bytes must be aligned to the alignment of
+/// the key type (memory maps are always aligned to a page), and a view can only be read on a machine with the
+/// same endianness as the one it was written on. Everything else is stored in little endian.
+///
+/// The search algorithms are the same as those of [`HggLite`](crate::HggLite), so the results are identical.
+#[derive(Debug)]
+pub struct HggView<'a, M, K> {
+    metric: M,
+    root: usize,
+    keys: &'a [K],
+    /// The index of the first layer of each node in `edge_offsets`, followed by the total number of node layers.
+    layer_index: &'a [U64],
+    /// The index of the first edge of each layer of each node in `edges`, followed by the total number of edges.
+    edge_offsets: &'a [U64],
+    edges: &'a [U32],
+    deleted: &'a [u8],
+    deleted_len: usize,
+    params: SearchParams,
+}
+
+impl<'a, M, K> HggView<'a, M, K>
+where
+    M: Metric<K>,
+    K: FromBytes + Immutable,
+{
+    /// Creates a view over bytes produced by [`HggLite::to_view_bytes`](crate::HggLite::to_view_bytes).
+    ///
+    /// The structure of the graph is checked so that searches can't go out of bounds, which is linear in the size of the view.
+    /// The search parameters begin as those of the HGG that was written.
+    pub fn new(metric: M, bytes: &'a [u8]) -> Result<Self, HggViewError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(HggViewError::Truncated {
+                len: bytes.len(),
+                expected: HEADER_LEN,
+            });
+        }
+        if bytes[0..8] != MAGIC {
+            return Err(HggViewError::BadMagic);
+        }
+        let version = read_u32(bytes, 8);
+        if version != VIEW_VERSION {
+            return Err(HggViewError::UnsupportedVersion { version });
+        }
+        let size = read_u32(bytes, 12);
+        let align = read_u32(bytes, 16);
+        if size as usize != mem::size_of::<K>() || align as usize != mem::align_of::<K>() {
+            return Err(HggViewError::KeyLayoutMismatch { size, align });
+        }
+        let exclude_all_searched = read_u32(bytes, 20) != 0;
+        let nodes = read_u64(bytes, 24)?;
+        let root = read_u64(bytes, 32)?;
+        let search_extra_knn = read_u64(bytes, 40)?;
+        let node_layers = read_u64(bytes, 48)?;
+        let edges = read_u64(bytes, 56)?;
+
+        let mut offset = keys_offset::<K>();
+        let keys = take(bytes, &mut offset, nodes)?;
+        let layer_index = take(bytes, &mut offset, nodes.saturating_add(1))?;
+        let edge_offsets = take(bytes, &mut offset, node_layers.saturating_add(1))?;
+        let edges = take(bytes, &mut offset, edges)?;
+        let deleted: &[u8] = take(bytes, &mut offset, nodes)?;
+
+        let view = Self {
+            metric,
+            root,
+            keys,
+            layer_index,
+            edge_offsets,
+            edges,
+            deleted,
+            deleted_len: deleted.iter().filter(|&&deleted| deleted != 0).count(),
+            params: SearchParams {
+                search_extra_knn,
+                exclude_all_searched,
+            },
+        };
+        view.check()?;
+        Ok(view)
+    }
+
+    /// Checks that every index in the view is in bounds.
+    fn check(&self) -> Result<(), HggViewError> {
+        let edges = self.edges.len() as u64;
+        let node_layers = self.edge_offsets.len() as u64 - 1;
+        if self.layer_index[0].get() != 0 || self.edge_offsets[0].get() != 0 {
+            return Err(HggViewError::InvalidLayers { node: 0 });
+        }
+        if self.layer_index[self.len()].get() != node_layers {
+            return Err(HggViewError::InvalidLayers {
+                node: self.len().saturating_sub(1),
+            });
+        }
+        if self.edge_offsets[node_layers as usize].get() != edges {
+            return Err(HggViewError::InvalidEdges {
+                node: self.len().saturating_sub(1),
+                layer: 0,
+            });
+        }
+        for node in 0..self.len() {
+            let (start, end) = (
+                self.layer_index[node].get(),
+                self.layer_index[node + 1].get(),
+            );
+            if start >= end || end > node_layers {
+                return Err(HggViewError::InvalidLayers { node });
+            }
+        }
+        if !self.is_empty() {
+            // The root must be the only node on the top layer.
+            let top = (0..self.len())
+                .map(|node| self.node_layers(node))
+                .max()
+                .unwrap();
+            if self.root >= self.len()
+                || self.node_layers(self.root) != top
+                || (0..self.len()).any(|node| node != self.root && self.node_layers(node) == top)
+            {
+                return Err(HggViewError::InvalidRoot { root: self.root });
+            }
+        }
+        for node in 0..self.len() {
+            for layer in 0..self.node_layers(node) {
+                let index = self.layer_index[node].get() as usize + layer;
+                let (start, end) = (
+                    self.edge_offsets[index].get(),
+                    self.edge_offsets[index + 1].get(),
+                );
+                if start > end
+                    || end > edges
+                    || self.edges[start as usize..end as usize]
+                        .iter()
+                        .any(|neighbor| {
+                            let neighbor = neighbor.get() as usize;
+                            neighbor >= self.len() || self.node_layers(neighbor) <= layer
+                        })
+                {
+                    return Err(HggViewError::InvalidEdges { node, layer });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn node_layers(&self, node: usize) -> usize {
+        (self.layer_index[node + 1].get() - self.layer_index[node].get()) as usize
+    }
+
+    /// Returns the parameters used by [`Knn::knn`].
+    pub fn search_params(&self) -> SearchParams {
+        self.params
+    }
+
+    /// Sets the parameters used by [`Knn::knn`].
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.params = params;
+    }
+
+    /// Finds the `num` nearest neighbors of the query using the given search parameters
+    /// instead of the ones stored in the view.
+    ///
+    /// Returns the neighbors sorted from nearest to furthest.
+    pub fn knn_with(&self, query: &K, num: usize, params: &SearchParams) -> Vec<Neighbor<M::Unit>> {
+        let neighbors = if self.deleted_len == 0 {
+            self.search_knn_filtered(&mut Scratch::new(), query, num, params, |_| true, &mut ())
+        } else {
+            self.search_knn_filtered(
+                &mut Scratch::new(),
+                query,
+                num,
+                params,
+                |node| self.deleted[node] == 0,
+                &mut (),
+            )
+        };
+        neighbors
+            .into_iter()
+            .map(|(index, distance)| Neighbor { index, distance })
+            .collect()
+    }
+
+    /// Searches for the nearest neighbor greedily from the top layer to the bottom.
+    ///
+    /// It may return a node marked as deleted.
+    ///
+    /// Returns `(node, distance)`.
+    pub fn greedy(&self, query: &K) -> Option<(usize, M::Unit)> {
+        self.search_to_layer(0, query, &mut ())
+    }
+
+    /// Get the key of a node.
+    pub fn get_key(&self, node: usize) -> Option<&'a K> {
+        self.keys.get(node)
+    }
+
+    /// Returns the keys of every node in order.
+    pub fn keys(&self) -> &'a [K] {
+        self.keys
+    }
+
+    /// Checks if a node is marked as deleted.
+    pub fn is_deleted(&self, node: usize) -> bool {
+        self.deleted.get(node).is_some_and(|&deleted| deleted != 0)
+    }
+
+    /// Returns the number of nodes marked as deleted.
+    pub fn deleted_len(&self) -> usize {
+        self.deleted_len
+    }
+
+    /// Checks if the view is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the number of nodes in the view, including nodes marked as deleted.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the number of layers in the graph.
+    pub fn layers(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            self.node_layers(self.root)
+        }
+    }
+}
+
+impl<'a, M, K> SearchGraph for HggView<'a, M, K>
+where
+    M: Metric<K>,
+    K: FromBytes + Immutable,
+{
+    type M = M;
+    type K = K;
+    type Node = ViewNode;
+
+    fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    fn root(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.root)
+        }
+    }
+
+    fn layers(&self) -> usize {
+        HggView::layers(self)
+    }
+
+    fn len(&self) -> usize {
+        HggView::len(self)
+    }
+
+    fn key(&self, node: usize) -> &K {
+        &self.keys[node]
+    }
+
+    fn layer_node(&self, layer: usize, node: usize) -> ViewNode {
+        ViewNode { node, layer }
+    }
+
+    fn node_index(&self, node: &ViewNode) -> usize {
+        node.node
+    }
+
+    fn neighbors<'b>(&'b self, node: &'b ViewNode) -> impl Iterator<Item = (ViewNode, &'b K)> + 'b {
+        let index = self.layer_index[node.node].get() as usize + node.layer;
+        let edges = &self.edges
+            [self.edge_offsets[index].get() as usize..self.edge_offsets[index + 1].get() as usize];
+        let layer = node.layer;
+        edges.iter().map(move |neighbor| {
+            let node = neighbor.get() as usize;
+            (ViewNode { node, layer }, &self.keys[node])
+        })
+    }
+}
+
+impl<'a, M, K> Knn for HggView<'a, M, K>
+where
+    M: Metric<K>,
+    K: FromBytes + Immutable,
+{
+    type Ix = usize;
+    type Metric = M;
+    type Point = K;
+    type KnnIter = Vec<Neighbor<M::Unit>>;
+
+    fn knn(&self, query: &K, num: usize) -> Self::KnnIter {
+        self.knn_with(query, num, &self.params)
+    }
+}
+
+impl<'a, M, K> KnnPoints for HggView<'a, M, K>
+where
+    M: Metric<K>,
+    K: FromBytes + Immutable,
+{
+    fn get_point(&self, index: usize) -> &'_ K {
+        &self.keys[index]
+    }
+}
@@ -0,0 +1,70 @@
+#[macro_use]
+extern crate std;
+
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn insert_batch() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 10);
+
+        // Insert keys into HGG in two batches, using the original index as the value.
+        let half = keys.len() / 2;
+        assert_eq!(
+            hgg.insert_batch(keys[..half].iter().copied().zip(0..)),
+            0..half
+        );
+        assert_eq!(
+            hgg.insert_batch(keys[half..].iter().copied().zip(half..)),
+            half..keys.len()
+        );
+        assert_eq!(hgg.len(), keys.len());
+
+        for (ix, key) in keys.iter().enumerate() {
+            // Make sure that the best result is this key.
+            let nn = hgg.knn(key, 5)[0];
+            assert_eq!(nn.distance, 0);
+            assert_eq!(*hgg.get_value(nn.index).unwrap(), ix);
+        }
+
+        // The graph must also be usable when starting from nothing.
+        let hgg: Graph<Hamming, BitArray<32>, usize> =
+            Graph::from_batch(Hamming, keys.iter().copied().zip(0..));
+        for (ix, key) in keys.iter().enumerate() {
+            assert_eq!(*hgg.get_value(hgg.knn(key, 5)[0].index).unwrap(), ix);
+        }
+    }
+}
+
+hgg_tests! {
+    fn insert_batch_self_recall() {
+        let keys = random_keys(1 << 10);
+        // Use few neighbors so that some keys can't be found, which makes the comparison meaningful.
+        let mut serial: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(8);
+        for &key in &keys {
+            serial.insert(key, ());
+        }
+        let mut batch: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(8);
+        batch.insert_batch(keys.iter().map(|&key| (key, ())));
+
+        // The batch-built graph must find at least as many keys as the one built one key at a time.
+        let greedy = |hgg: &Graph<Hamming, BitArray<32>, ()>| {
+            keys.iter()
+                .filter(|key| hgg.greedy(key).unwrap().1 == 0)
+                .count()
+        };
+        let knn = |hgg: &Graph<Hamming, BitArray<32>, ()>| {
+            keys.iter()
+                .filter(|key| hgg.knn(key, 1)[0].distance == 0)
+                .count()
+        };
+        assert!(greedy(&batch) >= greedy(&serial));
+        assert!(knn(&batch) >= knn(&serial));
+    }
+}
@@ -0,0 +1,96 @@
+use bitarray::{BitArray, Hamming};
+use hgg::{BudgetOutcome, SearchBudget, SearchVisitor};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+/// Stops a search after a fixed number of distance evaluations.
+struct Limit {
+    distances: usize,
+    max: usize,
+}
+
+impl SearchVisitor<u32> for Limit {
+    fn distance(&mut self, _node: usize, _distance: u32) {
+        self.distances += 1;
+    }
+
+    fn stop(&mut self) -> bool {
+        self.distances >= self.max
+    }
+}
+
+hgg_tests! {
+    fn budget() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let keys = random_keys(1 << 10);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        for key in &keys {
+            // Without any limits, the search must be identical to a normal search.
+            let (neighbors, outcome) = hgg.knn_with_budget(key, 10, &SearchBudget::default());
+            assert_eq!(neighbors, hgg.knn(key, 10));
+            assert_eq!(outcome, BudgetOutcome::Completed);
+
+            // A visitor must be able to stop the search after an exact amount of work.
+            let mut limit = Limit {
+                distances: 0,
+                max: 50,
+            };
+            hgg.knn_visit(key, 10, &mut limit);
+            assert_eq!(limit.distances, 50);
+
+            // Running out of budget must still give the best results found so far.
+            let (_, stats) = hgg.knn_with_stats(key, 10, &hgg.search_params());
+            let budget = SearchBudget {
+                max_expansions: Some(stats.expansions / 2),
+                ..SearchBudget::default()
+            };
+            let (neighbors, outcome) = hgg.knn_with_budget(key, 10, &budget);
+            assert_eq!(outcome, BudgetOutcome::BudgetExhausted);
+            assert!(!neighbors.is_empty());
+            assert!(neighbors.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+            // A budget larger than the work done by the search must not stop it.
+            let budget = SearchBudget {
+                max_distance_evaluations: Some(stats.distance_evaluations + 1),
+                max_expansions: Some(stats.expansions + 1),
+                good_enough: None,
+            };
+            assert_eq!(
+                hgg.knn_with_budget(key, 10, &budget),
+                (hgg.knn(key, 10), BudgetOutcome::Completed)
+            );
+
+            // Finding the key itself must be good enough.
+            let budget = SearchBudget {
+                good_enough: Some(0),
+                ..SearchBudget::default()
+            };
+            let (neighbors, outcome) = hgg.knn_with_budget(key, 10, &budget);
+            assert_eq!(outcome, BudgetOutcome::GoodEnough);
+            assert_eq!(neighbors[0].distance, 0);
+        }
+
+        // Deleted nodes must not be good enough, since they can't be returned.
+        for node in (0..keys.len()).step_by(3) {
+            hgg.mark_deleted(node);
+        }
+        for node in (0..keys.len()).step_by(3) {
+            let budget = SearchBudget {
+                good_enough: Some(0),
+                ..SearchBudget::default()
+            };
+            let (neighbors, outcome) = hgg.knn_with_budget(&keys[node], 10, &budget);
+            assert_eq!(outcome, BudgetOutcome::Completed);
+            assert_eq!(neighbors, hgg.knn(&keys[node], 10));
+        }
+    }
+}
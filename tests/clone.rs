@@ -0,0 +1,50 @@
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn clone() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 9);
+        let (first, second) = keys.split_at(keys.len() / 2);
+
+        // Insert the first half of the keys, using the original index as the value.
+        for (ix, &key) in first.iter().enumerate() {
+            hgg.insert(key, ix);
+        }
+        hgg.mark_deleted(0);
+
+        // Take a snapshot and then keep modifying the original.
+        let snapshot = hgg.clone();
+        let representation = hgg.simple_representation();
+        assert_eq!(snapshot.simple_representation(), representation);
+        for (ix, &key) in second.iter().enumerate() {
+            hgg.insert(key, first.len() + ix);
+        }
+        hgg.remove(1);
+        assert_eq!(hgg.validate(), Ok(()));
+
+        // The snapshot must be unaffected by the changes to the original.
+        drop(hgg);
+        assert_eq!(snapshot.validate(), Ok(()));
+        assert_eq!(snapshot.simple_representation(), representation);
+        assert_eq!(snapshot.len(), first.len());
+        assert!(snapshot.is_deleted(0));
+        for (ix, key) in first.iter().enumerate().skip(1) {
+            let nn = snapshot.knn(key, 5)[0];
+            assert_eq!(nn.distance, 0);
+            assert_eq!(*snapshot.get_value(nn.index).unwrap(), ix);
+        }
+
+        // The snapshot can be modified on its own.
+        let mut snapshot = snapshot;
+        for (ix, &key) in second.iter().enumerate() {
+            snapshot.insert(key, first.len() + ix);
+        }
+        assert_eq!(snapshot.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,31 @@
+use bitarray::{BitArray, Hamming};
+use space::Knn;
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn collect_extend_into_iter() {
+        let keys = random_keys(1 << 9);
+        let (first, second) = keys.split_at(keys.len() / 2);
+
+        // Collect the first half and extend with the second half, using the original index as the value.
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = first.iter().copied().zip(0..).collect();
+        hgg.extend(second.iter().copied().zip(first.len()..));
+        assert_eq!(hgg.len(), keys.len());
+        assert_eq!(hgg.validate(), Ok(()));
+
+        // Make sure that the best result is this key.
+        for (ix, key) in keys.iter().enumerate() {
+            let nn = hgg.knn(key, 5)[0];
+            assert_eq!(nn.distance, 0);
+            assert_eq!(*hgg.get_value(nn.index).unwrap(), ix);
+        }
+
+        // Every entry comes back out in order.
+        let entries: Vec<(BitArray<32>, usize)> = hgg.into_iter().collect();
+        assert_eq!(entries, keys.iter().copied().zip(0..).collect::<Vec<_>>());
+    }
+}
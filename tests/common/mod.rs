@@ -0,0 +1,53 @@
+//! Fixtures shared by the integration tests.
+//!
+//! Every test file is compiled as its own crate and only uses some of these, so unused ones are allowed.
+#![allow(dead_code, unused_macros)]
+
+use bitarray::BitArray;
+use rand::{Rng, SeedableRng};
+
+/// Generates `count` random byte arrays, which are the same on every run.
+pub fn random_bytes(count: usize) -> Vec<[u8; 32]> {
+    // Use a PRNG with good statistical properties for generating 64-bit numbers.
+    let mut rng = rand_xoshiro::Xoshiro256PlusPlus::seed_from_u64(0);
+
+    // Generate random keys.
+    (&mut rng)
+        .sample_iter::<[u8; 32], _>(rand::distributions::Standard)
+        .take(count)
+        .collect()
+}
+
+/// Generates `count` random keys, which are the same on every run.
+pub fn random_keys(count: usize) -> Vec<BitArray<32>> {
+    random_bytes(count).into_iter().map(BitArray::new).collect()
+}
+
+/// Defines every test once for `Hgg` and once for `HggLite`.
+///
+/// Each test becomes a module with an `hgg` and a `lite` test. Inside of the test, `Graph` is an alias of the
+/// type being tested, so `Graph::new` and `Graph<M, K, V>` work for both.
+macro_rules! hgg_tests {
+    ($($(#[$attr:meta])* fn $name:ident() $body:block)*) => {$(
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[test]
+            $(#[$attr])*
+            fn hgg() {
+                #[allow(dead_code)]
+                type Graph<M, K, V, Ix = usize> = hgg::Hgg<M, K, V, Ix>;
+                $body
+            }
+
+            #[test]
+            $(#[$attr])*
+            fn lite() {
+                #[allow(dead_code)]
+                type Graph<M, K, V, Ix = usize> = hgg::HggLite<M, K, V, Ix>;
+                $body
+            }
+        }
+    )*};
+}
@@ -0,0 +1,62 @@
+use bitarray::{BitArray, Hamming};
+use space::KnnInsert;
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn connectivity() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        for key in random_keys(1 << 9) {
+            hgg.insert(key, ());
+        }
+
+        // Every layer is a single component.
+        let components = hgg.layer_components();
+        assert_eq!(components.len(), hgg.layers());
+        for (sizes, nodes) in components.iter().zip(hgg.histogram_layer_nodes()) {
+            assert_eq!(sizes, &[nodes]);
+        }
+
+        // The unreachable nodes are exactly the ones greedy search can't find.
+        let unreachable = hgg.unreachable();
+        for (node, key) in hgg.keys().enumerate() {
+            assert_eq!(unreachable.contains(&node), hgg.greedy(key).unwrap().1 != 0);
+        }
+    }
+}
+
+hgg_tests! {
+    fn repair_unreachable() {
+        // A sparse graph leaves many nodes where greedy search can't find them.
+        let mut sparse: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(4);
+        let mut repaired = sparse.clone();
+        repaired.set_repair_unreachable(true);
+        for key in random_keys(1 << 9) {
+            sparse.insert(key, ());
+            repaired.insert(key, ());
+        }
+        assert_eq!(repaired.validate(), Ok(()));
+        assert!(repaired.unreachable().len() < sparse.unreachable().len());
+    }
+}
+
+hgg_tests! {
+    fn missing_upper_neighbors() {
+        // Insertion raises every new node until it has a neighbor on the layer above.
+        let mut unfreshened: Graph<Hamming, BitArray<32>, ()> =
+            Graph::default().insert_knn(100).freshens(0);
+        let mut freshened: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        for key in random_keys(1 << 9) {
+            unfreshened.insert(key, ());
+            freshened.insert(key, ());
+        }
+        assert_eq!(unfreshened.missing_upper_neighbors(), vec![]);
+
+        // Freshening doesn't maintain it, so it isn't checked by validate.
+        assert!(!freshened.missing_upper_neighbors().is_empty());
+        assert_eq!(freshened.validate(), Ok(()));
+    }
+}
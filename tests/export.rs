@@ -0,0 +1,69 @@
+use bitarray::{BitArray, Hamming};
+use space::{KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn export() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+        hgg.mark_deleted(3);
+        let total_edges: usize = hgg.edges().iter().sum();
+
+        let mut json = String::new();
+        hgg.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let nodes = json["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), hgg.len());
+        for (index, node) in nodes.iter().enumerate() {
+            assert_eq!(node["id"], index);
+            assert_eq!(node["deleted"], index == 3);
+        }
+        // The heights add up to the number of nodes on every layer.
+        let heights: u64 = nodes
+            .iter()
+            .map(|node| node["height"].as_u64().unwrap())
+            .sum();
+        assert_eq!(
+            heights as usize,
+            hgg.histogram_layer_nodes().iter().sum::<usize>()
+        );
+        let representation = hgg.simple_representation();
+        let edges = json["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), total_edges);
+        for edge in edges {
+            let source = edge["source"].as_u64().unwrap() as usize;
+            let target = edge["target"].as_u64().unwrap() as usize;
+            let layer = edge["layer"].as_u64().unwrap() as usize;
+            assert!(source < target);
+            assert_eq!(
+                edge["distance"],
+                Hamming.distance(hgg.get_key(source).unwrap(), hgg.get_key(target).unwrap())
+            );
+            assert!(layer < hgg.layers());
+            if layer == 0 {
+                assert!(representation[0][source].contains(&target));
+            }
+        }
+
+        let mut dot = String::new();
+        hgg.write_dot(&mut dot).unwrap();
+        assert!(dot.starts_with("graph hgg {"));
+        assert_eq!(dot.matches(" -- ").count(), total_edges);
+        assert_eq!(dot.matches("height=").count(), hgg.len());
+
+        let mut graphml = String::new();
+        hgg.write_graphml(&mut graphml).unwrap();
+        assert_eq!(graphml.matches("<edge ").count(), total_edges);
+        assert_eq!(graphml.matches("<node ").count(), hgg.len());
+        assert!(graphml.trim_end().ends_with("</graphml>"));
+    }
+}
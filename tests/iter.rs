@@ -0,0 +1,40 @@
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn iter() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG, using the original index as the value.
+        for (ix, &key) in keys.iter().enumerate() {
+            hgg.insert(key, ix);
+        }
+
+        for (ix, key, &value) in hgg.iter() {
+            assert_eq!(key, &keys[ix]);
+            assert_eq!(value, ix);
+        }
+        assert_eq!(hgg.iter().count(), keys.len());
+        assert!(hgg.keys().eq(keys.iter()));
+        assert!(hgg.values().copied().eq(0..keys.len()));
+
+        // Count how many times each key is found as a neighbor of the others.
+        for (ix, key, value) in hgg.iter_mut() {
+            assert_eq!(key, &keys[ix]);
+            *value = 0;
+        }
+        for key in &keys {
+            for neighbor in hgg.knn(key, 5) {
+                *hgg.get_value_mut(neighbor.index).unwrap() += 1;
+            }
+        }
+        assert_eq!(hgg.values().sum::<usize>(), keys.len() * 5);
+        assert_eq!(hgg.get_value_mut(keys.len()), None);
+    }
+}
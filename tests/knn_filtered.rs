@@ -0,0 +1,74 @@
+use bitarray::{BitArray, Hamming};
+use space::{KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+// The number of keys that come from each camera.
+const CAMERA_KEYS: usize = 1 << 6;
+
+/// Finds the distances of the `num` nearest keys from `camera` using linear search.
+fn linear_knn_camera(
+    keys: &[BitArray<32>],
+    query: &BitArray<32>,
+    num: usize,
+    camera: usize,
+) -> Vec<u32> {
+    let mut distances: Vec<u32> = keys
+        .iter()
+        .enumerate()
+        .filter(|&(ix, _)| ix / CAMERA_KEYS == camera)
+        .map(|(_, key)| Hamming.distance(key, query))
+        .collect();
+    distances.sort_unstable();
+    distances.truncate(num);
+    distances
+}
+
+hgg_tests! {
+    fn knn_filtered_camera() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG, using the camera of the key as the value.
+        for (ix, &key) in keys.iter().enumerate() {
+            hgg.insert(key, ix / CAMERA_KEYS);
+        }
+
+        for key in &keys {
+            // Only search for keys from camera 2.
+            let neighbors = hgg.knn_filtered(key, 5, |_, _, &camera| camera == 2);
+            // Make sure that only keys from camera 2 were returned.
+            assert!(neighbors
+                .iter()
+                .all(|n| *hgg.get_value(n.index).unwrap() == 2));
+            // Make sure that the correct neighbors were found.
+            let distances: Vec<u32> = neighbors.iter().map(|n| n.distance).collect();
+            assert_eq!(distances, linear_knn_camera(&keys, key, 5, 2));
+        }
+    }
+}
+
+hgg_tests! {
+    fn knn_filtered_sparse() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        // When fewer nodes pass the filter than are asked for, the whole graph is searched to find all of them.
+        let accepted = [3, 100, 200];
+        for key in &keys {
+            let mut found: Vec<usize> = hgg
+                .knn_filtered(key, 5, |node, _, _| accepted.contains(&node))
+                .iter()
+                .map(|n| n.index)
+                .collect();
+            found.sort_unstable();
+            assert_eq!(found, accepted);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert, Metric};
+use std::collections::HashSet;
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn knn_iter() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let keys = random_keys(1 << 9);
+        assert_eq!(hgg.knn_iter(&keys[0]).next(), None);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        // Mark some nodes as deleted to make sure they are skipped.
+        for node in (0..keys.len()).step_by(7) {
+            hgg.mark_deleted(node);
+        }
+
+        for key in &keys {
+            // Taking the first neighbors must give the same neighbors as a normal search.
+            let neighbors: Vec<_> = hgg.knn_iter(key).take(10).collect();
+            assert_eq!(neighbors, hgg.knn(key, 10));
+
+            // Every node which isn't deleted must be yielded exactly once.
+            let neighbors: Vec<_> = hgg.knn_iter(key).collect();
+            assert_eq!(neighbors.len(), hgg.len() - hgg.deleted_len());
+            let unique: HashSet<usize> = neighbors.iter().map(|neighbor| neighbor.index).collect();
+            assert_eq!(unique.len(), neighbors.len());
+            assert!(unique.iter().all(|&node| !hgg.is_deleted(node)));
+        }
+    }
+}
+
+hgg_tests! {
+    fn knn_iter_order() {
+        let keys = random_keys(1 << 8);
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        // Each neighbor is the nearest of the nodes found so far, so a window as large as the graph is exact.
+        hgg.set_search_extra_knn(keys.len());
+        for key in &keys {
+            let distances: Vec<u32> = hgg
+                .knn_iter(key)
+                .map(|neighbor| neighbor.distance)
+                .collect();
+            let mut expected: Vec<u32> = keys
+                .iter()
+                .map(|other| Hamming.distance(key, other))
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(distances, expected);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use bitarray::{BitArray, Hamming};
+use hgg::{ExhaustiveKnn, LinearKnn};
+use space::{Knn, KnnInsert, KnnMap, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+#[test]
+fn linear_knn() {
+    let mut linear: LinearKnn<Hamming, BitArray<32>, usize> = LinearKnn::default();
+    let keys = random_keys(1 << 9);
+    let (keys, queries) = keys.split_at(keys.len() / 2);
+
+    // Insert keys, using the index as the value.
+    for (ix, &key) in keys.iter().enumerate() {
+        assert_eq!(linear.insert(key, ix), ix);
+    }
+    assert_eq!(linear.len(), keys.len());
+
+    for query in queries {
+        // Compute the correct neighbors by sorting every key by its distance.
+        let mut correct: Vec<(u32, usize)> = keys
+            .iter()
+            .enumerate()
+            .map(|(ix, key)| (Hamming.distance(query, key), ix))
+            .collect();
+        correct.sort_unstable();
+        correct.truncate(10);
+
+        let neighbors = linear.knn(query, 10);
+        let found: Vec<(u32, usize)> = neighbors.iter().map(|n| (n.distance, n.index)).collect();
+        assert_eq!(found, correct);
+        assert_eq!(linear.exhaustive_knn(query, 10), neighbors);
+        assert_eq!(*linear.get_value(neighbors[0].index), neighbors[0].index);
+    }
+    assert!(linear.knn(&queries[0], 0).is_empty());
+}
+
+hgg_tests! {
+    fn exhaustive_knn() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let mut linear: LinearKnn<Hamming, BitArray<32>, ()> = LinearKnn::default();
+        let keys = random_keys(1 << 9);
+
+        for &key in &keys {
+            hgg.insert(key, ());
+            linear.insert(key, ());
+        }
+        hgg.mark_deleted(0);
+
+        for key in &keys {
+            let neighbors = hgg.exhaustive_knn(key, 10);
+            assert!(neighbors.iter().all(|n| n.index != 0));
+            let mut expected = linear.knn(key, 11);
+            expected.retain(|n| n.index != 0);
+            expected.truncate(10);
+            assert_eq!(neighbors, expected);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate std;
+
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn mark_deleted_and_compact() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG, using the original index as the value.
+        for (ix, &key) in keys.iter().enumerate() {
+            hgg.insert(key, ix);
+        }
+
+        // Mark the oldest half of the keys as deleted.
+        for node in 0..keys.len() / 2 {
+            assert!(hgg.mark_deleted(node));
+        }
+        assert!(!hgg.mark_deleted(0));
+        assert!(!hgg.mark_deleted(keys.len()));
+        assert_eq!(hgg.deleted_len(), keys.len() / 2);
+        assert_eq!(hgg.len(), keys.len());
+
+        for (ix, key) in keys.iter().enumerate() {
+            let knn = hgg.knn(key, 5);
+            // Make sure that deleted nodes never appear in the results.
+            assert!(knn.iter().all(|n| !hgg.is_deleted(n.index)));
+            if ix >= keys.len() / 2 {
+                // Make sure that the best result is this key.
+                assert_eq!(knn[0].distance, 0);
+                assert_eq!(knn[0].index, ix);
+            }
+        }
+
+        // Physically remove the deleted nodes.
+        let map = hgg.compact();
+        assert_eq!(map.len(), keys.len());
+        assert_eq!(hgg.len(), keys.len() / 2);
+        assert_eq!(hgg.deleted_len(), 0);
+
+        for (ix, key) in keys.iter().enumerate() {
+            if ix < keys.len() / 2 {
+                assert_eq!(map[ix], None);
+            } else {
+                // Make sure that the node was moved to the new index and can still be found.
+                let node = map[ix].unwrap();
+                assert_eq!(*hgg.get_value(node).unwrap(), ix);
+                let nn = hgg.knn(key, 5)[0];
+                assert_eq!(nn.distance, 0);
+                assert_eq!(nn.index, node);
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use bitarray::{BitArray, Hamming};
+use hgg::NodeIndex;
+use space::{Knn, KnnInsert, Metric};
+use std::panic::{self, AssertUnwindSafe};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn node_index_u32() {
+        let mut wide: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let mut narrow: Graph<Hamming, BitArray<32>, (), u32> =
+            Graph::with_node_index(Hamming).insert_knn(100);
+        let keys = random_keys(1 << 10);
+
+        // Insert keys into both HGGs.
+        for &key in &keys {
+            assert_eq!(wide.insert(key, ()), narrow.insert(key, ()));
+        }
+
+        // The index type must not change the structure of the graph.
+        assert_eq!(narrow.validate(), Ok(()));
+        assert_eq!(wide.simple_representation(), narrow.simple_representation());
+
+        // Searches must find exactly the same neighbors.
+        for key in &keys {
+            assert_eq!(wide.knn(key, 10), narrow.knn(key, 10));
+        }
+    }
+}
+
+#[test]
+fn node_index_limits() {
+    assert_eq!(<u16 as NodeIndex>::max(), u16::MAX as usize);
+    assert_eq!(<u16 as NodeIndex>::new(1234).index(), 1234);
+    assert_eq!(<usize as NodeIndex>::max(), usize::MAX);
+}
+
+#[test]
+#[should_panic(expected = "node index exceeds the capacity of the node index type")]
+fn node_index_overflow() {
+    <u16 as NodeIndex>::new(u16::MAX as usize + 1);
+}
+
+/// Measures the distance between points on a line, which keeps large graphs cheap to build.
+struct Line;
+
+impl Metric<u32> for Line {
+    type Unit = u32;
+
+    fn distance(&self, a: &u32, b: &u32) -> u32 {
+        a.abs_diff(*b)
+    }
+}
+
+hgg_tests! {
+    fn node_index_u16_overflow() {
+        let mut hgg: Graph<Line, u32, (), u16> = Graph::with_node_index(Line).insert_knn(2);
+        hgg.insert_batch((0..1 << 16).map(|key| (key, ())));
+        assert_eq!(hgg.len(), 1 << 16);
+
+        // The node after the largest index must panic without changing the graph.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| hgg.insert(1 << 16, ())));
+        assert!(result.is_err());
+        assert_eq!(hgg.len(), 1 << 16);
+        assert_eq!(hgg.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "rayon")]
+
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn batch_matches_sequential() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        // The parallel searches must give exactly the same results as the sequential ones.
+        let knns: Vec<Vec<_>> = keys.iter().map(|k| hgg.knn(k, 10)).collect();
+        assert_eq!(hgg.knn_batch(&keys, 10), knns);
+        let nns: Vec<_> = keys.iter().map(|k| hgg.nn(k)).collect();
+        assert_eq!(hgg.nn_batch(&keys), nns);
+    }
+}
+
+hgg_tests! {
+    fn par_from_batch_matches_from_batch() {
+        let keys = random_keys(1 << 8);
+
+        // The parallel construction must build exactly the same graph as the sequential one.
+        let hgg: Graph<Hamming, BitArray<32>, ()> =
+            Graph::from_batch(Hamming, keys.iter().map(|&k| (k, ())));
+        let par_hgg: Graph<Hamming, BitArray<32>, ()> =
+            Graph::par_from_batch(Hamming, keys.iter().map(|&k| (k, ())));
+        assert_eq!(par_hgg.simple_representation(), hgg.simple_representation());
+    }
+}
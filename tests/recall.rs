@@ -0,0 +1,155 @@
+#![cfg(feature = "std")]
+
+use bitarray::{BitArray, Hamming};
+use hgg::{evaluate_recall, ExhaustiveKnn, LinearKnn};
+use space::{Knn, KnnInsert, Neighbor};
+use std::cell::Cell;
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+/// Always misses the nearest neighbor, to test results which are worse than exact.
+struct SkipNearest(LinearKnn<Hamming, BitArray<32>, ()>);
+
+impl Knn for SkipNearest {
+    type Ix = usize;
+    type Metric = Hamming;
+    type Point = BitArray<32>;
+    type KnnIter = Vec<Neighbor<u32>>;
+
+    fn knn(&self, query: &BitArray<32>, num: usize) -> Self::KnnIter {
+        self.0.knn(query, num + 1).into_iter().skip(1).collect()
+    }
+}
+
+impl ExhaustiveKnn for SkipNearest {
+    fn exhaustive_knn(&self, query: &BitArray<32>, num: usize) -> Vec<Neighbor<u32>> {
+        self.0.knn(query, num)
+    }
+}
+
+/// Has no exact neighbors for the queries outside of `evaluated`, so they are skipped, and counts the searches.
+struct SkipQueries {
+    linear: LinearKnn<Hamming, BitArray<32>, ()>,
+    evaluated: Vec<BitArray<32>>,
+    searches: Cell<usize>,
+}
+
+impl Knn for SkipQueries {
+    type Ix = usize;
+    type Metric = Hamming;
+    type Point = BitArray<32>;
+    type KnnIter = Vec<Neighbor<u32>>;
+
+    fn knn(&self, query: &BitArray<32>, num: usize) -> Self::KnnIter {
+        self.searches.set(self.searches.get() + 1);
+        self.linear.knn(query, num)
+    }
+}
+
+impl ExhaustiveKnn for SkipQueries {
+    fn exhaustive_knn(&self, query: &BitArray<32>, num: usize) -> Vec<Neighbor<u32>> {
+        if self.evaluated.contains(query) {
+            self.linear.knn(query, num)
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[test]
+fn recall_linear() {
+    let mut linear: LinearKnn<Hamming, BitArray<32>, ()> = LinearKnn::default();
+    let keys = random_keys(1 << 10);
+    let (keys, queries) = keys.split_at(keys.len() / 2);
+    for &key in keys {
+        linear.insert(key, ());
+    }
+
+    // Linear search is exact.
+    let report = evaluate_recall(&linear, queries, 10).unwrap();
+    assert_eq!(report.recall, 1.0);
+    assert_eq!(report.distance_ratio, 1.0);
+    assert!(report.queries_per_second > 0.0);
+}
+
+hgg_tests! {
+    fn recall() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 10);
+        let (keys, queries) = keys.split_at(keys.len() / 2);
+        for &key in keys {
+            hgg.insert(key, ());
+        }
+
+        let report = evaluate_recall(&hgg, queries, 10).unwrap();
+        assert!(report.recall > 0.5 && report.recall <= 1.0);
+        assert!(report.distance_ratio >= 1.0);
+        assert!(report.queries_per_second > 0.0);
+
+        // Every key finds itself.
+        let report = evaluate_recall(&hgg, &keys[..16], 1).unwrap();
+        assert_eq!(report.recall, 1.0);
+        assert_eq!(report.distance_ratio, 1.0);
+    }
+}
+
+#[test]
+fn recall_skipped() {
+    let mut linear: LinearKnn<Hamming, BitArray<32>, ()> = LinearKnn::default();
+    let keys = random_keys(1 << 10);
+
+    // Without any queries to evaluate, there is no report.
+    assert_eq!(evaluate_recall(&linear, &keys, 10), None);
+    for &key in &keys {
+        linear.insert(key, ());
+    }
+    assert_eq!(evaluate_recall(&linear, &[], 10), None);
+    assert_eq!(evaluate_recall(&linear, &keys, 0), None);
+}
+
+#[test]
+fn recall_zero_distance() {
+    let mut linear: LinearKnn<Hamming, BitArray<32>, ()> = LinearKnn::default();
+    let keys = random_keys(1 << 10);
+    let (keys, queries) = keys.split_at(keys.len() / 2);
+    for &key in keys {
+        linear.insert(key, ());
+    }
+    let index = SkipNearest(linear);
+
+    // Every key is its own exact nearest neighbor at distance zero, so none of them have a distance ratio.
+    let report = evaluate_recall(&index, keys, 1).unwrap();
+    assert_eq!(report.recall, 0.0);
+    assert_eq!(report.distance_ratio, f64::INFINITY);
+
+    // Queries without a distance ratio are left out of the average of the rest.
+    let mixed: Vec<BitArray<32>> = keys.iter().chain(queries).copied().collect();
+    let report = evaluate_recall(&index, &mixed, 1).unwrap();
+    assert!(report.recall < 0.5);
+    assert!(report.distance_ratio.is_finite());
+    assert!(report.distance_ratio > 1.0);
+}
+
+#[test]
+fn recall_skipped_not_timed() {
+    let mut linear: LinearKnn<Hamming, BitArray<32>, ()> = LinearKnn::default();
+    let keys = random_keys(1 << 10);
+    let (keys, queries) = keys.split_at(keys.len() / 2);
+    for &key in keys {
+        linear.insert(key, ());
+    }
+    let index = SkipQueries {
+        linear,
+        evaluated: queries[..16].to_vec(),
+        searches: Cell::new(0),
+    };
+
+    // Skipped queries aren't searched, so they count towards neither the time nor the number of queries.
+    let report = evaluate_recall(&index, queries, 10).unwrap();
+    assert_eq!(index.searches.get(), 16);
+    assert_eq!(report.recall, 1.0);
+    assert!(report.queries_per_second > 0.0);
+}
@@ -0,0 +1,54 @@
+#[macro_use]
+extern crate std;
+
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn remove_oldest_half() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG, using the original index as the value.
+        for (ix, &key) in keys.iter().enumerate() {
+            hgg.insert(key, ix);
+        }
+
+        // Remove the oldest half of the keys, like a sliding window.
+        let mut node = 0;
+        while node < hgg.len() {
+            if *hgg.get_value(node).unwrap() < keys.len() / 2 {
+                let (key, ix) = hgg.remove(node).unwrap();
+                assert_eq!(key, keys[ix]);
+            } else {
+                node += 1;
+            }
+        }
+        assert_eq!(hgg.len(), keys.len() / 2);
+        assert_eq!(hgg.remove(hgg.len()), None);
+
+        for (ix, key) in keys.iter().enumerate() {
+            let nn = hgg.knn(key, 5)[0];
+            if ix < keys.len() / 2 {
+                // Make sure that the removed key is gone.
+                assert_ne!(nn.distance, 0);
+            } else {
+                // Make sure that the best result is this key.
+                assert_eq!(nn.distance, 0);
+                assert_eq!(*hgg.get_value(nn.index).unwrap(), ix);
+            }
+        }
+
+        // Remove everything left and make sure the graph is still usable.
+        while hgg.remove(0).is_some() {}
+        assert!(hgg.is_empty());
+        assert_eq!(hgg.layers(), 0);
+        hgg.insert(keys[0], 0);
+        assert_eq!(hgg.knn(&keys[0], 1)[0].distance, 0);
+    }
+}
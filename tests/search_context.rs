@@ -0,0 +1,93 @@
+use bitarray::{BitArray, Hamming};
+use hgg::{Hgg, HggLite, SearchContext, VisitedSet};
+use space::{Knn, KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+/// Measures the hamming distance between borrowed keys.
+struct BorrowedHamming;
+
+impl<'a> Metric<&'a BitArray<32>> for BorrowedHamming {
+    type Unit = u32;
+
+    fn distance(&self, a: &&'a BitArray<32>, b: &&'a BitArray<32>) -> u32 {
+        Hamming.distance(*a, *b)
+    }
+}
+
+hgg_tests! {
+    fn knn_in() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let keys = random_keys(1 << 10);
+        let (keys, queries) = keys.split_at(keys.len() / 2);
+
+        // Insert keys into HGG.
+        for &key in keys {
+            hgg.insert(key, ());
+        }
+
+        // Reusing one context for every search must find exactly the same neighbors as allocating for each search.
+        let mut ctx = SearchContext::new();
+        for (ix, query) in queries.iter().enumerate() {
+            let num = 1 + ix % 16;
+            assert_eq!(hgg.knn_in(&mut ctx, query, num), hgg.knn(query, num));
+        }
+    }
+}
+
+#[test]
+fn knn_in_shared() {
+    let keys = random_keys(1 << 10);
+    let (keys, queries) = keys.split_at(keys.len() / 2);
+    let hgg: Hgg<Hamming, BitArray<32>, ()> =
+        Hgg::from_batch(Hamming, keys.iter().map(|&key| (key, ())));
+    let lite: HggLite<Hamming, BitArray<32>, ()> =
+        HggLite::from_batch(Hamming, keys.iter().map(|&key| (key, ())));
+
+    // A context can be passed back and forth between different kinds of graphs.
+    let mut ctx = SearchContext::new();
+    for query in queries {
+        assert_eq!(hgg.knn_in(&mut ctx, query, 10), hgg.knn(query, 10));
+        assert_eq!(lite.knn_in(&mut ctx, query, 10), lite.knn(query, 10));
+    }
+}
+
+hgg_tests! {
+    fn insert_in() {
+        let keys = random_keys(1 << 10);
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let mut reused: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming)
+            .insert_knn(100)
+            .visited(VisitedSet::Epoch);
+
+        // Reusing one context for every insertion must build exactly the same graph as allocating for each one.
+        let mut ctx = SearchContext::new();
+        for &key in &keys {
+            assert_eq!(reused.insert_in(&mut ctx, key, ()), hgg.insert(key, ()));
+        }
+        for key in &keys {
+            assert_eq!(reused.knn_in(&mut ctx, key, 10), hgg.knn(key, 10));
+        }
+    }
+}
+
+hgg_tests! {
+    fn insert_in_borrowed() {
+        let keys = random_keys(1 << 10);
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let mut borrowed: Graph<BorrowedHamming, &BitArray<32>, ()> =
+            Graph::new(BorrowedHamming).insert_knn(100);
+
+        // A context works with keys which borrow from elsewhere.
+        let mut ctx = SearchContext::new();
+        for key in &keys {
+            assert_eq!(borrowed.insert_in(&mut ctx, key, ()), hgg.insert(*key, ()));
+        }
+        for key in &keys {
+            assert_eq!(borrowed.knn_in(&mut ctx, &key, 10), hgg.knn(key, 10));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use bitarray::{BitArray, Hamming};
+use hgg::SearchParams;
+use space::{Knn, KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn knn_with_params() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        // Modify the parameters in place.
+        assert_eq!(hgg.search_params(), SearchParams::default());
+        hgg.set_search_extra_knn(32);
+        hgg.set_exclude_all_searched(true);
+        let params = SearchParams {
+            search_extra_knn: 32,
+            exclude_all_searched: true,
+        };
+        assert_eq!(hgg.search_params(), params);
+
+        let fast = SearchParams {
+            search_extra_knn: 0,
+            ..params
+        };
+        for key in &keys {
+            // Searching with the index's own parameters must be identical to a normal search.
+            assert_eq!(hgg.knn_with(key, 10, &params), hgg.knn(key, 10));
+            // Make sure that the best result is this key even without any extra neighbors.
+            assert_eq!(hgg.knn_with(key, 1, &fast)[0].distance, 0);
+        }
+    }
+}
+
+hgg_tests! {
+    fn knn_large_pool() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        for exclude_all_searched in [false, true] {
+            // A pool large enough to hold every node makes the search exhaustive.
+            let params = SearchParams {
+                search_extra_knn: keys.len(),
+                exclude_all_searched,
+            };
+            for key in &keys {
+                let mut correct: Vec<u32> = keys
+                    .iter()
+                    .map(|other| Hamming.distance(key, other))
+                    .collect();
+                correct.sort_unstable();
+                correct.truncate(10);
+                let distances: Vec<u32> = hgg
+                    .knn_with(key, 10, &params)
+                    .into_iter()
+                    .map(|neighbor| neighbor.distance)
+                    .collect();
+                assert_eq!(distances, correct);
+            }
+        }
+    }
+}
@@ -4,10 +4,15 @@
 extern crate std;
 
 use bitarray::{BitArray, Hamming};
-use hgg::{Hgg, HggLite};
+use hgg::{Hgg, HggLite, LegacyHgg, LegacyHggLite, VisitedSet};
 use rand::{Rng, SeedableRng};
+use serde_json::Value;
 use space::{Knn, KnnInsert};
 
+mod common;
+
+use common::random_keys;
+
 #[test]
 fn serde_json_bincode_round_trip_lite() {
     let mut hgg: HggLite<Hamming, BitArray<32>, ()> = HggLite::default().insert_knn(100);
@@ -40,6 +45,9 @@ fn serde_json_bincode_round_trip_lite() {
     let hgg: HggLite<Hamming, BitArray<32>, ()> =
         bincode::deserialize_from(bdata.as_slice()).expect("failed to deserialize with bincode");
 
+    // The deserialized graph must be intact.
+    assert_eq!(hgg.validate(), Ok(()));
+
     // Find the 10 nearest neighbors to every node again.
     let new_knns: Vec<Vec<_>> = keys.iter().map(|k| hgg.knn(k, 10)).collect();
 
@@ -79,9 +87,150 @@ fn serde_json_bincode_round_trip_hgg() {
     let hgg: Hgg<Hamming, BitArray<32>, ()> =
         bincode::deserialize_from(bdata.as_slice()).expect("failed to deserialize with bincode");
 
+    // The deserialized graph must be intact.
+    assert_eq!(hgg.validate(), Ok(()));
+
     // Find the 10 nearest neighbors to every node again.
     let new_knns: Vec<Vec<_>> = keys.iter().map(|k| hgg.knn(k, 10)).collect();
 
     // If they are exactly equal, everything worked as expected.
     assert_eq!(old_knns, new_knns);
 }
+
+/// Defines the versioned container test for a graph type, given the type with the other strategy and the
+/// legacy layout of the graph type.
+macro_rules! versioned_container_tests {
+    ($($name:ident: $graph:ident, $other:ident, $legacy:ident;)*) => {$(
+        #[test]
+        fn $name() {
+            type Graph<M, K, V> = $graph<M, K, V>;
+            type Other<M, K, V> = $other<M, K, V>;
+            type Legacy<M, K, V> = $legacy<M, K, V>;
+
+            let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+            let keys = random_keys(1 << 8);
+
+            // Insert keys into HGG.
+            for &key in &keys {
+                hgg.insert(key, ());
+            }
+
+            // A truncated file must be rejected.
+            let mut bdata = vec![];
+            bincode::serialize_into(&mut bdata, &hgg).expect("failed to serialize with bincode");
+            assert!(
+                bincode::deserialize_from::<_, Graph<Hamming, BitArray<32>, ()>>(
+                    &bdata[..bdata.len() / 2]
+                )
+                .is_err()
+            );
+
+            // A file with the wrong checksum must be rejected.
+            let value = serde_json::to_value(&hgg).expect("failed to serialize with serde_json");
+            let mut corrupt = value.clone();
+            corrupt["checksum"] =
+                Value::from(corrupt["checksum"].as_u64().unwrap().wrapping_add(1));
+            let error =
+                serde_json::from_value::<Graph<Hamming, BitArray<32>, ()>>(corrupt).unwrap_err();
+            assert!(error.to_string().contains("checksum"));
+
+            // A file written with the other strategy must be rejected.
+            let error = serde_json::from_value::<Other<Hamming, BitArray<32>, ()>>(value.clone())
+                .unwrap_err();
+            assert!(error.to_string().contains("strategy"));
+
+            // Settings must survive a round trip.
+            hgg.set_repair_unreachable(true);
+            hgg.set_visited(VisitedSet::Epoch);
+            let repair = serde_json::to_value(&hgg).expect("failed to serialize with serde_json");
+            let round_trip: Graph<Hamming, BitArray<32>, ()> =
+                serde_json::from_value(repair.clone())
+                    .expect("failed to deserialize with serde_json");
+            let round_trip = serde_json::to_value(&round_trip).unwrap();
+            assert_eq!(round_trip["hgg"]["repair_unreachable"], true);
+            assert_eq!(round_trip["hgg"]["visited"], "Epoch");
+
+            // Settings added after the format was versioned take their defaults when they are missing.
+            let mut missing = repair;
+            missing["hgg"]
+                .as_object_mut()
+                .unwrap()
+                .remove("repair_unreachable");
+            missing["hgg"].as_object_mut().unwrap().remove("visited");
+            let missing: Graph<Hamming, BitArray<32>, ()> =
+                serde_json::from_value(missing).expect("failed to deserialize with serde_json");
+            let missing = serde_json::to_value(&missing).unwrap();
+            assert_eq!(missing["hgg"]["repair_unreachable"], false);
+            assert_eq!(missing["hgg"]["visited"], "Hash");
+
+            // Recreate the layout from before the format was versioned and migrate it.
+            let mut legacy = value["hgg"].clone();
+            legacy.as_object_mut().unwrap().remove("deleted");
+            for node in legacy["nodes"].as_array_mut().unwrap() {
+                node.as_object_mut().unwrap().remove("deleted");
+            }
+            let legacy = serde_json::json!({ "hgg": legacy });
+            assert!(
+                serde_json::from_value::<Graph<Hamming, BitArray<32>, ()>>(legacy.clone()).is_err()
+            );
+            let hgg: Graph<Hamming, BitArray<32>, ()> =
+                serde_json::from_value::<Legacy<Hamming, BitArray<32>, ()>>(legacy)
+                    .expect("failed to migrate the legacy layout")
+                    .into();
+            assert_eq!(hgg.validate(), Ok(()));
+            for key in &keys {
+                // Make sure that the best result is this key.
+                assert_eq!(hgg.knn(key, 5)[0].distance, 0);
+            }
+        }
+    )*};
+}
+
+versioned_container_tests! {
+    serde_versioned_container_lite: HggLite, Hgg, LegacyHggLite;
+    serde_versioned_container_hgg: Hgg, HggLite, LegacyHgg;
+}
+
+#[test]
+fn serde_node_index_u32() {
+    let mut wide: Hgg<Hamming, BitArray<32>, ()> = Hgg::default().insert_knn(100);
+    let mut narrow: Hgg<Hamming, BitArray<32>, (), u32> =
+        Hgg::with_node_index(Hamming).insert_knn(100);
+    let keys = random_keys(1 << 8);
+
+    // Insert keys into both HGGs.
+    for &key in &keys {
+        wide.insert(key, ());
+        narrow.insert(key, ());
+    }
+
+    // The narrower index type must take up less space when serialized.
+    let wide_data = bincode::serialize(&wide).expect("failed to serialize with bincode");
+    let narrow_data = bincode::serialize(&narrow).expect("failed to serialize with bincode");
+    assert!(narrow_data.len() < wide_data.len());
+
+    // Reading the file with the wrong index type must be rejected.
+    assert!(bincode::deserialize::<Hgg<Hamming, BitArray<32>, ()>>(&narrow_data).is_err());
+
+    let hgg: Hgg<Hamming, BitArray<32>, (), u32> =
+        bincode::deserialize(&narrow_data).expect("failed to deserialize with bincode");
+    assert_eq!(hgg.validate(), Ok(()));
+    for key in &keys {
+        assert_eq!(wide.knn(key, 10), hgg.knn(key, 10));
+    }
+}
+
+#[test]
+fn serde_node_index_overflow() {
+    let mut hgg: Hgg<Hamming, BitArray<32>, (), u16> = Hgg::with_node_index(Hamming);
+    hgg.insert(random_keys(1)[0], ());
+
+    // Repeat the only node until there are more nodes than a `u16` can index.
+    let mut value = serde_json::to_value(&hgg).expect("failed to serialize with serde_json");
+    let node = value["hgg"]["nodes"][0].clone();
+    value["hgg"]["nodes"] = Value::Array(vec![node; (1 << 16) + 1]);
+
+    // This must be an error rather than a panic.
+    let error = serde_json::from_value::<Hgg<Hamming, BitArray<32>, (), u16>>(value).unwrap_err();
+    assert!(error.to_string().contains("node index type"));
+}
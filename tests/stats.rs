@@ -0,0 +1,53 @@
+use bitarray::{BitArray, Hamming};
+use space::{Knn, Metric};
+use std::{cell::Cell, rc::Rc};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+/// Counts the number of times the distance is computed.
+#[derive(Clone, Default)]
+struct CountingHamming(Rc<Cell<usize>>);
+
+impl Metric<BitArray<32>> for CountingHamming {
+    type Unit = u32;
+
+    fn distance(&self, a: &BitArray<32>, b: &BitArray<32>) -> u32 {
+        self.0.set(self.0.get() + 1);
+        Hamming.distance(a, b)
+    }
+}
+
+hgg_tests! {
+    fn stats() {
+        let metric = CountingHamming::default();
+        let mut hgg: Graph<CountingHamming, BitArray<32>, ()> =
+            Graph::new(metric.clone()).insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            let (node, stats) = hgg.insert_with_stats(key, ());
+            assert_eq!(node, hgg.len() - 1);
+            if node > 1 {
+                assert!(stats.distance_evaluations > 0);
+                assert!(stats.expansions > 0);
+                assert!(stats.pool_size > 0);
+            }
+        }
+
+        let params = hgg.search_params();
+        for key in &keys {
+            let before = metric.0.get();
+            let (neighbors, stats) = hgg.knn_with_stats(key, 5, &params);
+            // Every distance computed by the search must be counted.
+            assert_eq!(stats.distance_evaluations, metric.0.get() - before);
+            assert_eq!(neighbors, hgg.knn(key, 5));
+            assert_eq!(stats.greedy_hops.len(), hgg.layers());
+            assert!(stats.pool_size >= neighbors.len());
+            assert!(stats.expansions > 0);
+        }
+    }
+}
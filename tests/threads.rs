@@ -0,0 +1,38 @@
+use bitarray::{BitArray, Hamming};
+use space::{Knn, KnnInsert};
+use std::{sync::Arc, thread};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn shared_search() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = Arc::new(random_keys(1 << 8));
+
+        // Insert keys into HGG.
+        for &key in keys.iter() {
+            hgg.insert(key, ());
+        }
+
+        // Search the same HGG from several threads at once.
+        let hgg = Arc::new(hgg);
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let hgg = hgg.clone();
+                let keys = keys.clone();
+                thread::spawn(move || {
+                    for key in keys.iter() {
+                        // Make sure that the best result is this key.
+                        assert_eq!(hgg.knn(key, 5)[0].distance, 0);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}
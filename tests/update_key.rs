@@ -0,0 +1,62 @@
+use bitarray::{BitArray, Hamming};
+use hgg::HggLite;
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn update_key() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 9);
+        let (old_keys, new_keys) = keys.split_at(keys.len() / 2);
+
+        // Insert keys into HGG, using the original index as the value.
+        for (ix, &key) in old_keys.iter().enumerate() {
+            hgg.insert(key, ix);
+        }
+
+        // Move every other node to a new key.
+        for node in (0..hgg.len()).step_by(2) {
+            assert_eq!(hgg.update_key(node, new_keys[node]), Some(old_keys[node]));
+        }
+        assert_eq!(hgg.update_key(hgg.len(), new_keys[0]), None);
+        assert_eq!(hgg.validate(), Ok(()));
+
+        for node in 0..hgg.len() {
+            let key = if node % 2 == 0 {
+                // Make sure that the old key is gone.
+                assert_ne!(hgg.knn(&old_keys[node], 5)[0].distance, 0);
+                new_keys[node]
+            } else {
+                old_keys[node]
+            };
+            // Make sure that the best result is this node with the same value.
+            let nn = hgg.knn(&key, 5)[0];
+            assert_eq!(nn.distance, 0);
+            assert_eq!(nn.index, node);
+            assert_eq!(hgg.get(node), Some((&key, &node)));
+        }
+    }
+}
+
+#[test]
+fn update_key_small_lite() {
+    let keys = random_keys(1 << 9);
+    let mut hgg: HggLite<Hamming, BitArray<32>, ()> = HggLite::default();
+
+    // Updating the only node just changes its key.
+    hgg.insert(keys[0], ());
+    assert_eq!(hgg.update_key(0, keys[1]), Some(keys[0]));
+    assert_eq!(hgg.knn(&keys[0], 1)[0].index, 0);
+    assert_eq!(hgg.validate(), Ok(()));
+
+    // Updating the root of two nodes has to pick a new root.
+    hgg.insert(keys[2], ());
+    let root = hgg.greedy(&keys[1]).unwrap().0;
+    assert_eq!(hgg.update_key(root, keys[3]), Some(keys[1]));
+    assert_eq!(hgg.validate(), Ok(()));
+    assert_eq!(hgg.knn(&keys[3], 1)[0].distance, 0);
+}
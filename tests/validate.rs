@@ -0,0 +1,37 @@
+use bitarray::{BitArray, Hamming};
+use space::KnnInsert;
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn validate() {
+        let mut hgg: Graph<Hamming, BitArray<32>, usize> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+        assert_eq!(hgg.validate(), Ok(()));
+
+        // Insert keys into HGG, checking the graph after every insertion.
+        for (ix, &key) in keys.iter().enumerate() {
+            hgg.insert(key, ix);
+            assert_eq!(hgg.validate(), Ok(()));
+        }
+
+        // Delete some nodes in every way possible, checking the graph after every step.
+        for node in 0..keys.len() / 4 {
+            hgg.mark_deleted(node * 2);
+            assert_eq!(hgg.validate(), Ok(()));
+        }
+        for _ in 0..keys.len() / 4 {
+            hgg.remove(1);
+            assert_eq!(hgg.validate(), Ok(()));
+        }
+        hgg.compact();
+        assert_eq!(hgg.validate(), Ok(()));
+
+        // Insert the keys again as a batch.
+        hgg.insert_batch(keys.iter().copied().zip(0..));
+        assert_eq!(hgg.validate(), Ok(()));
+    }
+}
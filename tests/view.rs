@@ -0,0 +1,101 @@
+#![cfg(feature = "zerocopy")]
+
+use hgg::{HggLite, HggView, HggViewError};
+use space::{Knn, KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_bytes;
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Hamming;
+
+impl Metric<[u8; 32]> for Hamming {
+    type Unit = u32;
+
+    fn distance(&self, a: &[u8; 32], b: &[u8; 32]) -> u32 {
+        a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+}
+
+hgg_tests! {
+    fn view_matches() {
+        let mut hgg: Graph<Hamming, [u8; 32], ()> = Graph::default().insert_knn(100);
+        let keys = random_bytes(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+        hgg.mark_deleted(7);
+
+        let bytes = hgg.to_view_bytes();
+        let view = HggView::new(Hamming, &bytes).unwrap();
+        assert_eq!(view.len(), hgg.len());
+        assert_eq!(view.layers(), hgg.layers());
+        assert!(view.is_deleted(7));
+        for (ix, key) in keys.iter().enumerate() {
+            assert_eq!(view.get_key(ix), Some(key));
+            assert_eq!(view.knn(key, 10), hgg.knn(key, 10));
+            assert_eq!(view.greedy(key), hgg.greedy(key));
+        }
+    }
+}
+
+#[test]
+fn view_rejects_invalid_bytes() {
+    let mut hgg: HggLite<Hamming, [u8; 32], ()> = HggLite::default().insert_knn(100);
+    for key in random_bytes(1 << 8) {
+        hgg.insert(key, ());
+    }
+    let bytes = hgg.to_view_bytes();
+
+    // An empty view is valid.
+    let empty = HggLite::<Hamming, [u8; 32], ()>::default().to_view_bytes();
+    let view = HggView::new(Hamming, &empty).unwrap();
+    assert!(view.is_empty());
+    assert!(view.knn(&[0; 32], 10).is_empty());
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = 0;
+    assert_eq!(
+        HggView::<Hamming, [u8; 32]>::new(Hamming, &bad_magic).unwrap_err(),
+        HggViewError::BadMagic
+    );
+
+    assert!(matches!(
+        HggView::<Hamming, [u8; 32]>::new(Hamming, &bytes[..bytes.len() - 1]).unwrap_err(),
+        HggViewError::Truncated { .. }
+    ));
+
+    // The last edge comes right before the deleted flags. Point it at a node which doesn't exist.
+    let mut bad_edge = bytes.clone();
+    let edge = bytes.len() - hgg.len() - 4;
+    bad_edge[edge..edge + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        HggView::<Hamming, [u8; 32]>::new(Hamming, &bad_edge).unwrap_err(),
+        HggViewError::InvalidEdges { .. }
+    ));
+
+    // The root is stored right after the header. Point it at a node which isn't on the top layer.
+    let mut root = [0; 8];
+    root.copy_from_slice(&bytes[32..40]);
+    let root = u64::from_le_bytes(root) as usize;
+    let mut bad_root = bytes.clone();
+    bad_root[32..40].copy_from_slice(&(((root + 1) % hgg.len()) as u64).to_le_bytes());
+    assert_eq!(
+        HggView::<Hamming, [u8; 32]>::new(Hamming, &bad_root).unwrap_err(),
+        HggViewError::InvalidRoot {
+            root: (root + 1) % hgg.len()
+        }
+    );
+
+    // Point it at a node which doesn't exist.
+    let mut bad_root = bytes;
+    bad_root[32..40].copy_from_slice(&(hgg.len() as u64).to_le_bytes());
+    assert_eq!(
+        HggView::<Hamming, [u8; 32]>::new(Hamming, &bad_root).unwrap_err(),
+        HggViewError::InvalidRoot { root: hgg.len() }
+    );
+}
@@ -0,0 +1,93 @@
+use bitarray::{BitArray, Hamming};
+use hgg::SearchVisitor;
+use space::{Knn, KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+/// Records every event of a search.
+#[derive(Debug, Default)]
+struct Trace {
+    layers: Vec<usize>,
+    distances: Vec<(usize, u32)>,
+    hops: Vec<(usize, u32)>,
+    expansions: Vec<usize>,
+    inserted: Vec<usize>,
+    pool: Vec<(usize, u32)>,
+    pool_size: usize,
+}
+
+impl SearchVisitor<u32> for Trace {
+    fn enter_layer(&mut self, layer: usize) {
+        self.layers.push(layer);
+    }
+
+    fn distance(&mut self, node: usize, distance: u32) {
+        self.distances.push((node, distance));
+    }
+
+    fn hop(&mut self, node: usize, distance: u32) {
+        self.hops.push((node, distance));
+    }
+
+    fn expand(&mut self, node: usize) {
+        self.expansions.push(node);
+    }
+
+    fn pool_insert(&mut self, node: usize, distance: u32) {
+        self.inserted.push(node);
+        self.pool.push((node, distance));
+    }
+
+    fn pool_evict(&mut self, node: usize, distance: u32) {
+        let index = self
+            .pool
+            .iter()
+            .position(|&entry| entry == (node, distance))
+            .unwrap();
+        self.pool.remove(index);
+    }
+
+    fn pool_finish(&mut self, size: usize) {
+        self.pool_size = size;
+    }
+}
+
+hgg_tests! {
+    fn visit() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        for key in &keys {
+            let mut trace = Trace::default();
+            let neighbors = hgg.knn_visit(key, 5, &mut trace);
+            assert_eq!(neighbors, hgg.knn(key, 5));
+
+            // The search descends from the top layer to the bottom layer.
+            assert!(trace.layers.iter().copied().eq((0..hgg.layers()).rev()));
+            // Every distance is reported with the node it was computed to.
+            for &(node, distance) in &trace.distances {
+                assert_eq!(Hamming.distance(hgg.get_key(node).unwrap(), key), distance);
+            }
+            // Every hop moves closer to the query.
+            assert!(trace.hops.windows(2).all(|hops| hops[1].1 < hops[0].1));
+            // Only nodes in the pool are expanded.
+            assert!(trace
+                .expansions
+                .iter()
+                .all(|node| trace.inserted.contains(node)));
+            // The nodes left in the pool are the results of the search.
+            assert_eq!(trace.pool.len(), trace.pool_size);
+            for neighbor in &neighbors {
+                assert!(trace.pool.contains(&(neighbor.index, neighbor.distance)));
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use bitarray::{BitArray, Hamming};
+use hgg::{SearchContext, VisitedSet};
+use space::{Knn, KnnInsert};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+hgg_tests! {
+    fn visited_epoch() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let keys = random_keys(1 << 10);
+        let (keys, queries) = keys.split_at(keys.len() / 2);
+        let (first, second) = keys.split_at(keys.len() / 2);
+
+        let mut ctx = SearchContext::new();
+        for exclude_all_searched in [false, true] {
+            hgg.set_exclude_all_searched(exclude_all_searched);
+            // Grow the graph between searches so that the epoch array must grow with it.
+            for keys in [first, second] {
+                for &key in keys {
+                    hgg.insert(key, ());
+                }
+
+                // The epoch array must find exactly the same neighbors as the hash set.
+                for (ix, query) in queries.iter().enumerate() {
+                    let num = 1 + ix % 16;
+                    hgg.set_visited(VisitedSet::Hash);
+                    let expected = hgg.knn(query, num);
+                    hgg.set_visited(VisitedSet::Epoch);
+                    assert_eq!(hgg.knn_in(&mut ctx, query, num), expected);
+                    // Searches without a context use the chosen mode too.
+                    assert_eq!(hgg.knn(query, num), expected);
+                }
+            }
+            hgg = Graph::new(Hamming).insert_knn(100);
+        }
+    }
+}
+
+hgg_tests! {
+    fn visited_epoch_insert() {
+        let keys = random_keys(1 << 10);
+        let mut hash: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming).insert_knn(100);
+        let mut epoch: Graph<Hamming, BitArray<32>, ()> = Graph::new(Hamming)
+            .insert_knn(100)
+            .visited(VisitedSet::Epoch);
+
+        // Insertion searches with the chosen mode, which must build exactly the same graph.
+        for &key in &keys {
+            assert_eq!(epoch.insert(key, ()), hash.insert(key, ()));
+        }
+        assert_eq!(epoch.simple_representation(), hash.simple_representation());
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn visited_epoch_batch() {
+    use hgg::{Hgg, HggLite};
+
+    let keys = random_keys(1 << 10);
+    let (keys, queries) = keys.split_at(keys.len() / 2);
+    let mut hgg: Hgg<Hamming, BitArray<32>, ()> =
+        Hgg::from_batch(Hamming, keys.iter().map(|&key| (key, ())));
+    let mut lite: HggLite<Hamming, BitArray<32>, ()> =
+        HggLite::from_batch(Hamming, keys.iter().map(|&key| (key, ())));
+
+    // Each thread keeps its own epoch array, which must find exactly the same neighbors as the hash set.
+    let expected = hgg.knn_batch(queries, 10);
+    hgg.set_visited(VisitedSet::Epoch);
+    assert_eq!(hgg.knn_batch(queries, 10), expected);
+    let expected = lite.knn_batch(queries, 10);
+    lite.set_visited(VisitedSet::Epoch);
+    assert_eq!(lite.knn_batch(queries, 10), expected);
+}
@@ -0,0 +1,71 @@
+use bitarray::{BitArray, Hamming};
+use space::{KnnInsert, Metric};
+
+#[macro_use]
+mod common;
+
+use common::random_keys;
+
+const RADIUS: u32 = 112;
+
+/// Finds every key within the radius using linear search.
+fn linear_within(keys: &[BitArray<32>], query: &BitArray<32>) -> Vec<usize> {
+    (0..keys.len())
+        .filter(|&ix| Hamming.distance(&keys[ix], query) <= RADIUS)
+        .collect()
+}
+
+hgg_tests! {
+    fn within_radius() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        for key in &keys {
+            let neighbors = hgg.within(key, RADIUS);
+            // Make sure the neighbors are sorted from nearest to furthest.
+            assert!(neighbors.windows(2).all(|w| w[0].distance <= w[1].distance));
+            // Make sure that the best result is this key.
+            assert_eq!(neighbors[0].distance, 0);
+            // Make sure that exactly the keys within the radius were found.
+            let mut found: Vec<usize> = neighbors.iter().map(|n| n.index).collect();
+            found.sort_unstable();
+            assert_eq!(found, linear_within(&keys, key));
+        }
+    }
+}
+
+hgg_tests! {
+    fn within_slack() {
+        let mut hgg: Graph<Hamming, BitArray<32>, ()> = Graph::default().insert_knn(100);
+        let keys = random_keys(1 << 8);
+
+        // Insert keys into HGG.
+        for &key in &keys {
+            hgg.insert(key, ());
+        }
+
+        for key in &keys {
+            // Without any slack, only nodes connected through nodes within the radius can be found.
+            let narrow: Vec<usize> = hgg
+                .within_with(key, RADIUS, 0)
+                .iter()
+                .map(|n| n.index)
+                .collect();
+            let expected = linear_within(&keys, key);
+            assert!(narrow.iter().all(|index| expected.contains(index)));
+            // With enough slack to search the whole graph, every node within the radius must be found.
+            let mut found: Vec<usize> = hgg
+                .within_with(key, RADIUS, keys.len())
+                .iter()
+                .map(|n| n.index)
+                .collect();
+            found.sort_unstable();
+            assert_eq!(found, expected);
+        }
+    }
+}